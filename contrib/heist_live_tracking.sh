@@ -1,22 +1,127 @@
 # Heist Live Tracking Shell Integration
 # Add this to your ~/.bashrc or ~/.zshrc to enable real-time history tracking for Heist
+#
+# Record format: <timestamp>|@exit=<status>,dur=<milliseconds>,cwd=<directory>,host=<hostname>|<command>
+# Metadata values are percent-encoded; older `<timestamp>|<command>` records are still read.
+#
+# Set HEIST_LIVE_CHAIN=1 before sourcing to make the log tamper-evident: each record then ends its
+# metadata with `chain=<sha256 of the previous record's hash, a newline and this record without
+# the field>`, and `heist verify` reports the first record that no longer matches.
+# Needs sha256sum or shasum; flock, where available, keeps concurrent shells from forking the chain.
 
 export HEIST_LIVE_HISTORY="$HOME/.heist_live_history"
+HEIST_LIVE_HOST="${HOSTNAME:-${HOST:-$(uname -n 2>/dev/null)}}"
+
+# Escape the characters that delimit metadata
+heist_live_encode() {
+  local value="$1"
+  value="${value//"%"/%25}"
+  value="${value//","/%2C}"
+  value="${value//"|"/%7C}"
+  printf '%s' "$value"
+}
+
+# SHA-256 of stdin as hex
+heist_live_sha256() {
+  local sum
+  if command -v sha256sum >/dev/null 2>&1; then
+    sum=$(sha256sum) || return 1
+  else
+    sum=$(shasum -a 256) || return 1
+  fi
+  printf '%s' "${sum%% *}"
+}
+
+# Append a record chained to the last one in the log
+heist_live_append_chained() {
+  local ts="$1" meta="$2" last prev hash
+  prev=0000000000000000000000000000000000000000000000000000000000000000
+  if [ -s "$HEIST_LIVE_HISTORY" ]; then
+    last=$(tail -n 1 "$HEIST_LIVE_HISTORY")
+    last="${last#*"|@"}"
+    last="${last%%"|"*}"
+    case ",$last" in
+      *",chain="*) prev="${last##*chain=}" ;;
+    esac
+  fi
+  hash=$(printf '%s\n%s' "$prev" "$ts|@$meta|$HEIST_LIVE_CMD" | heist_live_sha256) || return 1
+  printf "%s|@%s,chain=%s|%s\n" "$ts" "$meta" "$hash" "$HEIST_LIVE_CMD" >> "$HEIST_LIVE_HISTORY"
+}
+
+# Number and text of the most recent history entry
+heist_live_last() {
+  local line
+  if [ -n "$ZSH_VERSION" ]; then
+    line=$(fc -l -1 2>/dev/null) || return 1
+  else
+    line=$(HISTTIMEFORMAT= history 1 2>/dev/null) || return 1
+  fi
+  line="${line#"${line%%[![:space:]]*}"}"
+  HEIST_LIVE_NUM="${line%%[[:space:]]*}"
+  HEIST_LIVE_CMD="${line#"$HEIST_LIVE_NUM"}"
+  HEIST_LIVE_CMD="${HEIST_LIVE_CMD#"${HEIST_LIVE_CMD%%[![:space:]]*}"}"
+  HEIST_LIVE_CMD="${HEIST_LIVE_CMD//$'\n'/ }"
+}
+
+# Current time in microseconds (needs $EPOCHREALTIME: bash 5+, zsh/datetime); zsh gives more
+# than 6 fractional digits, so the fraction is padded or cut to exactly 6
+heist_live_now() {
+  local frac="${EPOCHREALTIME#*[.,]}000000"
+  HEIST_LIVE_NOW=
+  [ -n "$EPOCHREALTIME" ] && HEIST_LIVE_NOW="${EPOCHREALTIME%%[.,]*}${frac:0:6}"
+}
+
+# Start time, set just before a command runs
+heist_live_start() {
+  heist_live_now
+  HEIST_LIVE_T0="$HEIST_LIVE_NOW"
+}
 
 heist_live_track() {
-  # Only log if not a duplicate of the last command
-  if [ -n "$BASH_COMMAND" ]; then
-    if [ ! -f "$HEIST_LIVE_HISTORY" ] || [ "$(tail -n 1 "$HEIST_LIVE_HISTORY" 2>/dev/null)" != "$BASH_COMMAND" ]; then
-      printf "%s|%s\n" "$(date +'%Y-%m-%dT%H:%M:%S%z')" "$BASH_COMMAND" >> "$HEIST_LIVE_HISTORY"
+  # Must run first so $? still holds the command's exit status (not `status`: read-only in zsh)
+  local rc=$? meta now dir ts
+  meta="exit=$rc"
+  if [ "${HEIST_LIVE_T0:-0}" != 0 ]; then
+    heist_live_now
+    now="$HEIST_LIVE_NOW"
+    [ -n "$now" ] && meta="$meta,dur=$(( (now - HEIST_LIVE_T0) / 1000 ))"
+    HEIST_LIVE_T0=
+  fi
+  heist_live_last || return 0
+  # Only log when a new command was entered (not on an empty prompt)
+  if [ -n "$HEIST_LIVE_CMD" ] && [ "$HEIST_LIVE_NUM" != "$HEIST_LIVE_SEEN" ]; then
+    HEIST_LIVE_SEEN="$HEIST_LIVE_NUM"
+    # The command started in the directory of the previous prompt
+    dir="${HEIST_LIVE_PWD:-$PWD}"
+    meta="$meta,cwd=$(heist_live_encode "$dir")"
+    [ -n "$HEIST_LIVE_HOST" ] && meta="$meta,host=$(heist_live_encode "$HEIST_LIVE_HOST")"
+    ts="$(date +'%Y-%m-%dT%H:%M:%S%z')"
+    if [ "${HEIST_LIVE_CHAIN:-0}" = 1 ]; then
+      if command -v flock >/dev/null 2>&1; then
+        { flock 9 && heist_live_append_chained "$ts" "$meta"; } 9>>"$HEIST_LIVE_HISTORY"
+      else
+        heist_live_append_chained "$ts" "$meta"
+      fi
+    else
+      printf "%s|@%s|%s\n" "$ts" "$meta" "$HEIST_LIVE_CMD" >> "$HEIST_LIVE_HISTORY"
     fi
   fi
+  HEIST_LIVE_PWD="$PWD"
+  return 0
 }
 
-# For Bash
+# Skip whatever was already in history when the shell started
+heist_live_last && HEIST_LIVE_SEEN="$HEIST_LIVE_NUM"
+HEIST_LIVE_PWD="$PWD"
+
+# For Bash: PS0 is expanded right before a command runs; the array subscript records the start time
 if [ -n "$BASH_VERSION" ]; then
   PROMPT_COMMAND="heist_live_track; $PROMPT_COMMAND"
+  PS0='${HEIST_LIVE_NONE[HEIST_LIVE_T0=10#0${EPOCHREALTIME//[!0-9]/}]}'"$PS0"
 fi
 # For Zsh
 if [ -n "$ZSH_VERSION" ]; then
-  precmd_functions+=(heist_live_track)
+  zmodload zsh/datetime 2>/dev/null
+  preexec_functions+=(heist_live_start)
+  precmd_functions=(heist_live_track $precmd_functions)
 fi
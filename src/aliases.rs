@@ -0,0 +1,685 @@
+//! Alias, abbreviation and function definitions from the user's shell configuration
+//! Loads definitions from rc files or `alias` output, expands them in history and suggests new aliases
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use dirs::home_dir;
+use serde::Serialize;
+
+use crate::cli::{AliasSyntax, CliArgs, ShellType};
+use crate::models::HistoryEntry;
+use crate::tokenizer::{is_assignment, is_operator, pipeline_spans, pipelines, stage_program, tokenize};
+
+// Logging macro for errors
+macro_rules! log_error {
+    ($($arg:tt)*) => {{
+        let msg = format!($($arg)*);
+        eprintln!("[heist error] {}", msg);
+        if let Ok(mut f) = OpenOptions::new().create(true).append(true).open("heist_error.log") {
+            let _ = writeln!(f, "{}", msg);
+        }
+    }};
+}
+
+/// Kind of shell definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DefinitionKind {
+    Alias,
+    Abbr,
+    Function,
+}
+
+/// An alias, abbreviation or function defined in a shell config file
+#[derive(Debug, Clone, Serialize)]
+pub struct AliasDef {
+    pub name: String,
+    /// Replacement text; for functions, the body joined with `; `
+    pub expansion: String,
+    pub kind: DefinitionKind,
+    /// File the definition came from (`-` for stdin)
+    pub source: String,
+    /// 1-based line number within `source`
+    pub line: usize,
+    /// Whether the definition can be substituted for its name (aliases, abbreviations and
+    /// single-command functions)
+    pub expandable: bool,
+}
+
+/// Shell config files scanned for definitions, relative to the home directory
+const RC_FILES: [&str; 8] = [
+    ".bashrc",
+    ".bash_aliases",
+    ".bash_profile",
+    ".profile",
+    ".zshrc",
+    ".zsh_aliases",
+    ".zprofile",
+    ".config/fish/config.fish",
+];
+
+/// Fish directories whose `*.fish` files are scanned, relative to the home directory
+const FISH_DIRS: [&str; 2] = [".config/fish/conf.d", ".config/fish/functions"];
+
+/// Load definitions from `--aliases-from` (a file, or `-` for stdin) or else from the rc files.
+/// Loaded once per process so the TUI refresh thread does not re-read stdin.
+pub fn definitions(args: &CliArgs) -> &'static [AliasDef] {
+    static DEFS: OnceLock<Vec<AliasDef>> = OnceLock::new();
+    DEFS.get_or_init(|| {
+        let loaded = match args.aliases_from.as_deref() {
+            Some("-") => read_definitions(io::stdin().lock(), "-"),
+            Some(path) => load_definitions_file(Path::new(path)),
+            None => Ok(load_shell_definitions()),
+        };
+        loaded.unwrap_or_else(|e| {
+            log_error!("Failed to load alias definitions: {:#}", e);
+            Vec::new()
+        })
+    })
+}
+
+/// Scan the user's bash, zsh and fish config files for definitions
+pub fn load_shell_definitions() -> Vec<AliasDef> {
+    let Some(home) = home_dir() else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = RC_FILES.iter().map(|f| home.join(f)).collect();
+    for dir in FISH_DIRS {
+        if let Ok(read_dir) = std::fs::read_dir(home.join(dir)) {
+            let mut fish_files: Vec<PathBuf> = read_dir
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "fish"))
+                .collect();
+            fish_files.sort();
+            paths.extend(fish_files);
+        }
+    }
+    paths
+        .iter()
+        .filter(|p| p.exists())
+        .filter_map(|p| load_definitions_file(p).ok())
+        .flatten()
+        .collect()
+}
+
+/// Parse definitions from a single config file or saved `alias` output
+pub fn load_definitions_file(path: &Path) -> Result<Vec<AliasDef>> {
+    let file = File::open(path).context(format!("Failed to open definitions file: {:?}", path))?;
+    read_definitions(BufReader::new(file), &path.display().to_string())
+}
+
+/// Parse definitions from any reader; `source` labels the results
+fn read_definitions<R: Read>(reader: R, source: &str) -> Result<Vec<AliasDef>> {
+    let lines = BufReader::new(reader)
+        .lines()
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to read alias definitions")?;
+    Ok(parse_definitions(&lines, source))
+}
+
+/// Parse alias, abbr and function definitions from shell source or `alias`/`abbr` output.
+/// Understands bash/zsh `alias a='b'`, zsh's bare `a='b'` listing, fish `alias a 'b'`,
+/// `abbr -a a b`, `name() { ... }`, `function name { ... }` and fish `function name ... end`.
+pub fn parse_definitions(lines: &[String], source: &str) -> Vec<AliasDef> {
+    let mut defs = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        let line_no = i + 1;
+        i += 1;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let tokens = tokenize(line);
+        match tokens.first().map(|t| t.as_str()) {
+            Some("alias") => {
+                for (name, expansion) in parse_alias_tokens(&tokens[1..]) {
+                    defs.push(definition(name, expansion, DefinitionKind::Alias, source, line_no));
+                }
+            },
+            Some("abbr") => {
+                if let Some((name, expansion)) = parse_abbr_tokens(&tokens[1..]) {
+                    defs.push(definition(name, expansion, DefinitionKind::Abbr, source, line_no));
+                }
+            },
+            _ => {
+                if let Some((name, body, consumed)) = parse_function(lines, i - 1) {
+                    i = i - 1 + consumed;
+                    let mut def = definition(name, body.join("; "), DefinitionKind::Function, source, line_no);
+                    def.expandable = body.len() == 1;
+                    if def.expandable {
+                        def.expansion = strip_arg_placeholders(&body[0]);
+                    }
+                    defs.push(def);
+                } else if tokens.len() == 1 && !line.starts_with("export ") {
+                    // zsh `alias` listing prints bare `name='value'` lines
+                    if let Some((name, value)) = tokens[0].split_once('=') {
+                        if is_definition_name(name) && line.contains(['\'', '"']) {
+                            defs.push(definition(name.to_string(), value.to_string(), DefinitionKind::Alias, source, line_no));
+                        }
+                    }
+                }
+            },
+        }
+    }
+    defs
+}
+
+fn definition(name: String, expansion: String, kind: DefinitionKind, source: &str, line: usize) -> AliasDef {
+    AliasDef { name, expansion, kind, source: source.to_string(), line, expandable: true }
+}
+
+fn is_definition_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || "_-.:+@".contains(c))
+}
+
+/// `alias a='b' c=d` (bash/zsh) or `alias a 'b'` (fish)
+fn parse_alias_tokens(tokens: &[String]) -> Vec<(String, String)> {
+    let words: Vec<&str> = tokens.iter()
+        .take_while(|t| !is_operator(t))
+        .skip_while(|t| t.starts_with('-'))
+        .map(|t| t.as_str())
+        .collect();
+    if let [name, rest @ ..] = words.as_slice() {
+        if !rest.is_empty() && !name.contains('=') && is_definition_name(name) {
+            return vec![(name.to_string(), rest.join(" "))];
+        }
+    }
+    words.iter()
+        .filter_map(|w| w.split_once('='))
+        .filter(|(name, _)| is_definition_name(name))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// `abbr -a [--position command] [--] name expansion...`
+fn parse_abbr_tokens(tokens: &[String]) -> Option<(String, String)> {
+    let mut words = tokens.iter().take_while(|t| !is_operator(t)).peekable();
+    let mut rest: Vec<&str> = vec![];
+    while let Some(word) = words.next() {
+        match word.as_str() {
+            "--position" | "-p" | "--set-cursor" | "--regex" | "--function" | "-f" => { words.next(); },
+            "--erase" | "-e" | "--show" | "-s" | "--list" | "-l" | "--query" | "-q" | "--rename" | "-r" => return None,
+            w if w.starts_with('-') && rest.is_empty() => {},
+            w => rest.push(w),
+        }
+    }
+    if rest.len() < 2 || !is_definition_name(rest[0]) {
+        return None;
+    }
+    Some((rest[0].to_string(), rest[1..].join(" ")))
+}
+
+/// Recognise a function definition starting at `start` and return its name, the non-empty
+/// body lines and the number of lines consumed
+fn parse_function(lines: &[String], start: usize) -> Option<(String, Vec<String>, usize)> {
+    let header = lines[start].trim();
+    let brace_next = lines.get(start + 1).is_some_and(|l| l.trim_start().starts_with('{'));
+    let fish = header.starts_with("function ") && !header.contains('{') && !header.contains("()") && !brace_next;
+    let name = if let Some(rest) = header.strip_prefix("function ") {
+        rest.split(|c: char| c.is_whitespace() || c == '(' || c == '{').next()?.to_string()
+    } else {
+        let (name, _) = header.split_once("()")?;
+        name.trim().to_string()
+    };
+    if !is_definition_name(&name) {
+        return None;
+    }
+    let mut body = vec![];
+    if fish {
+        // Fish: count nested blocks until the matching `end`
+        let mut depth = 1;
+        let mut consumed = 1;
+        for line in &lines[start + 1..] {
+            consumed += 1;
+            let t = line.trim();
+            let first = t.split_whitespace().next().unwrap_or("");
+            if matches!(first, "if" | "for" | "while" | "switch" | "begin" | "function") {
+                depth += 1;
+            } else if first == "end" || first == "end;" {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((name, body, consumed));
+                }
+            }
+            if !t.is_empty() && !t.starts_with('#') {
+                body.push(t.to_string());
+            }
+        }
+        return Some((name, body, consumed));
+    }
+    // POSIX: collect text between the outermost braces
+    if !header.contains('{') && !brace_next {
+        return None;
+    }
+    let mut depth = 0;
+    let mut current = String::new();
+    for (consumed, line) in lines[start..].iter().take(200).enumerate() {
+        for c in line.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    if depth == 1 { continue; }
+                },
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        push_body_line(&mut body, &current);
+                        return Some((name, body, consumed + 1));
+                    }
+                },
+                _ => {},
+            }
+            if depth > 0 {
+                current.push(c);
+            }
+        }
+        push_body_line(&mut body, &current);
+        current.clear();
+    }
+    None
+}
+
+fn push_body_line(body: &mut Vec<String>, text: &str) {
+    let t = text.trim().trim_end_matches(';').trim();
+    if !t.is_empty() && !t.starts_with('#') {
+        body.push(t.to_string());
+    }
+}
+
+/// Remove argument forwarding (`"$@"`, `$*`, `$argv`) from a one-line function body
+fn strip_arg_placeholders(body: &str) -> String {
+    let mut out = body.to_string();
+    for placeholder in ["\"$@\"", "\"$*\"", "$@", "$*", "$argv"] {
+        out = out.replace(placeholder, "");
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Expand the command word of every chained or piped command in a line. Quoted or escaped
+/// words are not expanded, as in the shell.
+pub fn expand_command(command: &str, defs: &[AliasDef]) -> String {
+    let words: Vec<Range<usize>> = pipeline_spans(command).into_iter()
+        .flatten()
+        .filter_map(|stage| stage.into_iter().find(|(token, _)| !is_assignment(token)))
+        .filter(|(token, span)| command[span.clone()] == *token)
+        .map(|(_, span)| span)
+        .collect();
+    let mut out = command.to_string();
+    for span in words.into_iter().rev() {
+        if let Some(expansion) = expand_word(&command[span.clone()], defs) {
+            out.replace_range(span, &expansion);
+        }
+    }
+    out
+}
+
+/// Expansion of an alias name, following alias chains a few levels deep
+fn expand_word(word: &str, defs: &[AliasDef]) -> Option<String> {
+    let mut body = word.to_string();
+    let mut seen: Vec<String> = vec![];
+    for _ in 0..8 {
+        let word_end = body.find(char::is_whitespace).unwrap_or(body.len());
+        let word = &body[..word_end];
+        if word.is_empty() || seen.iter().any(|s| s == word) {
+            break;
+        }
+        let Some(def) = defs.iter().rev().find(|d| d.expandable && d.name == word) else {
+            break;
+        };
+        seen.push(word.to_string());
+        body = format!("{}{}", def.expansion, &body[word_end..]);
+    }
+    (!seen.is_empty()).then_some(body)
+}
+
+/// Replace each entry's command with its alias-expanded form
+pub fn expand_history(entries: &mut [HistoryEntry], defs: &[AliasDef]) {
+    if defs.is_empty() {
+        return;
+    }
+    for entry in entries.iter_mut() {
+        entry.command = expand_command(&entry.command, defs);
+    }
+}
+
+/// Usage of one definition across the analysed history
+#[derive(Debug, Clone, Serialize)]
+pub struct AliasUsage {
+    pub definition: AliasDef,
+    /// Commands invoking the definition by name
+    pub uses: usize,
+    pub last_used: Option<DateTime<Local>>,
+    /// Commands that typed the expansion out in full instead of using the name
+    pub typed_out: usize,
+}
+
+impl AliasUsage {
+    /// Fish abbreviations expand as they are typed, so history only ever holds the expansion
+    pub fn effective_uses(&self) -> usize {
+        match self.definition.kind {
+            DefinitionKind::Abbr => self.uses + self.typed_out,
+            _ => self.uses,
+        }
+    }
+}
+
+/// Count how often each definition is used, when it was last used, and how often its
+/// expansion was typed out in full
+pub fn alias_audit(history: &[HistoryEntry], defs: &[AliasDef]) -> Vec<AliasUsage> {
+    let mut usage: Vec<AliasUsage> = defs.iter()
+        .map(|d| AliasUsage { definition: d.clone(), uses: 0, last_used: None, typed_out: 0 })
+        .collect();
+    let expansions: Vec<Vec<String>> = defs.iter()
+        .map(|d| if d.expandable { tokenize(&d.expansion) } else { vec![] })
+        .collect();
+    for entry in history {
+        for stage in pipelines(&entry.command).into_iter().flatten() {
+            let Some(program) = stage_program(&stage) else { continue };
+            for (u, expansion) in usage.iter_mut().zip(&expansions) {
+                if program == u.definition.name {
+                    u.uses += 1;
+                    if entry.timestamp > u.last_used {
+                        u.last_used = entry.timestamp;
+                    }
+                } else if !expansion.is_empty()
+                    && expansion[0] != u.definition.name
+                    && stage.starts_with(expansion)
+                {
+                    u.typed_out += 1;
+                    if u.definition.kind == DefinitionKind::Abbr && entry.timestamp > u.last_used {
+                        u.last_used = entry.timestamp;
+                    }
+                }
+            }
+        }
+    }
+    usage.sort_by(|a, b| b.effective_uses().cmp(&a.effective_uses()).then_with(|| a.definition.name.cmp(&b.definition.name)));
+    usage
+}
+
+/// Print the definition inventory, unused definitions and typed-out expansions
+pub fn print_alias_audit(usage: &[AliasUsage]) {
+    println!("\nAlias & function inventory ({} definitions):", usage.len());
+    if usage.is_empty() {
+        println!("No aliases, abbreviations or functions found in shell config.");
+        return;
+    }
+    println!("{:<16} {:<9} {:>6}  {:<17} Source", "Name", "Kind", "Uses", "Last used");
+    for u in usage {
+        let last = u.last_used.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "-".to_string());
+        println!("{:<16} {:<9} {:>6}  {:<17} {}:{}", u.definition.name, format!("{:?}", u.definition.kind), u.effective_uses(), last, u.definition.source, u.definition.line);
+    }
+    let unused: Vec<&AliasUsage> = usage.iter().filter(|u| u.effective_uses() == 0).collect();
+    println!("\nNever used in the analysed period ({}):", unused.len());
+    for u in &unused {
+        println!("  {:<16} = {}", u.definition.name, u.definition.expansion);
+    }
+    let mut typed: Vec<&AliasUsage> = usage.iter()
+        .filter(|u| u.typed_out > 0 && u.definition.kind != DefinitionKind::Abbr)
+        .collect();
+    typed.sort_by_key(|u| std::cmp::Reverse(u.typed_out));
+    println!("\nTyped out in full despite a definition ({}):", typed.len());
+    for u in typed {
+        println!("  {:<40} {:>4}x  → use '{}'", u.definition.expansion, u.typed_out, u.definition.name);
+    }
+}
+
+/// Builtins of bash, zsh and fish that a suggested alias must not shadow
+const SHELL_BUILTINS: [&str; 72] = [
+    "alias", "bg", "bind", "break", "builtin", "caller", "cd", "command", "compgen", "complete",
+    "continue", "declare", "dirs", "disown", "echo", "enable", "eval", "exec", "exit", "export",
+    "false", "fc", "fg", "getopts", "hash", "help", "history", "jobs", "kill", "let", "local",
+    "logout", "popd", "printf", "pushd", "pwd", "read", "readonly", "return", "set", "shift",
+    "shopt", "source", "suspend", "test", "times", "trap", "true", "type", "typeset", "ulimit",
+    "umask", "unalias", "unset", "wait", "abbr", "and", "begin", "end", "functions", "not", "or",
+    "status", "string", "math", "emulate", "setopt", "unsetopt", "whence", "where", "which", "r",
+];
+
+/// A suggested alias for a long, frequently typed command
+#[derive(Debug, Clone, Serialize)]
+pub struct AliasSuggestion {
+    pub name: String,
+    pub command: String,
+    pub count: usize,
+}
+
+/// Default alias syntax for a shell: fish gets `abbr`, everything else POSIX `alias`
+pub fn default_alias_syntax(shell: &ShellType) -> AliasSyntax {
+    match shell {
+        ShellType::Fish => AliasSyntax::Fish,
+        ShellType::Zsh => AliasSyntax::Zsh,
+        _ => AliasSyntax::Bash,
+    }
+}
+
+/// Mnemonic for a command: initials of the program, subcommands and flags (`git commit -m` → `gcm`).
+/// Words after the first flag are treated as arguments and skipped.
+pub fn mnemonic(command: &str) -> String {
+    let mut name = String::new();
+    let mut seen_flag = false;
+    for token in tokenize(command).iter().take_while(|t| !is_operator(t)) {
+        let letters: String = if let Some(long) = token.strip_prefix("--") {
+            seen_flag = true;
+            long.chars().filter(|c| c.is_ascii_alphanumeric()).take(1).collect()
+        } else if let Some(short) = token.strip_prefix('-') {
+            seen_flag = true;
+            short.chars().filter(|c| c.is_ascii_alphanumeric()).take(2).collect()
+        } else if seen_flag {
+            continue;
+        } else {
+            // Basename for paths such as ./scripts/deploy.sh
+            let word = token.rsplit('/').next().unwrap_or(token);
+            word.chars().filter(|c| c.is_ascii_alphanumeric()).take(1).collect()
+        };
+        name.push_str(&letters.to_ascii_lowercase());
+        if name.len() >= 4 {
+            break;
+        }
+    }
+    name.truncate(4);
+    name
+}
+
+/// Names of all executables on `$PATH`
+pub fn path_executables() -> HashSet<String> {
+    let mut names = HashSet::new();
+    let Some(path) = std::env::var_os("PATH") else {
+        return names;
+    };
+    for dir in std::env::split_paths(&path) {
+        if let Ok(read_dir) = std::fs::read_dir(dir) {
+            names.extend(read_dir.filter_map(|e| e.ok()).map(|e| e.file_name().to_string_lossy().into_owned()));
+        }
+    }
+    names
+}
+
+/// Names a new alias or function must not use: `reserved` (executables on `$PATH`),
+/// shell builtins and existing definitions
+pub fn taken_names(defs: &[AliasDef], reserved: &HashSet<String>) -> HashSet<String> {
+    let mut taken: HashSet<String> = reserved.clone();
+    taken.extend(SHELL_BUILTINS.iter().map(|b| b.to_string()));
+    taken.extend(defs.iter().map(|d| d.name.clone()));
+    taken
+}
+
+/// Pick the first of `base`, `base2`, `base3`, ... that clashes with nothing in `taken`
+pub fn unique_name(base: &str, taken: &HashSet<String>) -> String {
+    let base = if base.len() < 2 { format!("{}a", base) } else { base.to_string() };
+    if !taken.contains(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| format!("{}{}", base, n))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap_or(base)
+}
+
+/// Suggest mnemonic aliases for the 10 most frequent commands longer than 15 characters.
+/// Commands an existing definition already expands to are skipped, and names are checked
+/// against `reserved` (executables on `$PATH`), shell builtins and existing definitions.
+pub fn alias_suggestions(history: &[HistoryEntry], defs: &[AliasDef], reserved: &HashSet<String>) -> Vec<AliasSuggestion> {
+    use std::collections::HashMap;
+    let mut freq: HashMap<&str, usize> = HashMap::new();
+    for entry in history {
+        let cmd = entry.command.trim();
+        if cmd.len() > 15 && !defs.iter().any(|d| d.expandable && d.expansion == cmd) {
+            *freq.entry(cmd).or_insert(0) += 1;
+        }
+    }
+    let mut freq_vec: Vec<_> = freq.into_iter().collect();
+    freq_vec.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let mut taken = taken_names(defs, reserved);
+    freq_vec.into_iter()
+        .take(10)
+        .map(|(cmd, count)| {
+            let name = unique_name(&mnemonic(cmd), &taken);
+            taken.insert(name.clone());
+            AliasSuggestion { name, command: cmd.to_string(), count }
+        })
+        .collect()
+}
+
+/// Render one alias definition in the syntax of the target shell
+pub fn format_alias(name: &str, command: &str, syntax: AliasSyntax) -> String {
+    match syntax {
+        AliasSyntax::Bash | AliasSyntax::Zsh => format!("alias {}='{}'", name, command.replace('\'', r"'\''")),
+        AliasSyntax::Fish => format!("abbr -a {} '{}'", name, command.replace('\\', r"\\").replace('\'', r"\'")),
+        AliasSyntax::Nu => format!("alias {} = {}", name, command),
+    }
+}
+
+/// Write suggestions to a file that the target shell can source
+pub fn write_alias_file(path: &Path, suggestions: &[AliasSuggestion], syntax: AliasSyntax) -> Result<()> {
+    let mut f = File::create(path).context(format!("Failed to create alias file: {:?}", path))?;
+    writeln!(f, "# Aliases suggested by heist ({:?} syntax)", syntax).context("Failed to write alias file")?;
+    for s in suggestions {
+        writeln!(f, "{}  # used {} times", format_alias(&s.name, &s.command, syntax), s.count).context("Failed to write alias file")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_rc_definitions() {
+        let defs = parse_definitions(&lines(r#"
+# comment
+alias gco='git checkout' gst="git status"
+alias -g G='| grep'
+abbr -a --position command gp git push
+gcm() { git commit -m "$@"; }
+function deploy {
+  make build
+  ./push.sh
+}
+function fish_ll
+    ls -la $argv
+end
+ll='ls -la'
+export FOO='bar'
+"#), "rc");
+        let names: Vec<&str> = defs.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["gco", "gst", "G", "gp", "gcm", "deploy", "fish_ll", "ll"]);
+        assert_eq!(defs[0].expansion, "git checkout");
+        assert_eq!(defs[2].expansion, "| grep");
+        assert_eq!(defs[3].kind, DefinitionKind::Abbr);
+        assert_eq!(defs[3].expansion, "git push");
+        assert_eq!(defs[4].expansion, "git commit -m");
+        assert!(defs[4].expandable);
+        assert!(!defs[5].expandable);
+        assert_eq!(defs[5].line, 7);
+        assert_eq!(defs[6].expansion, "ls -la");
+    }
+
+    #[test]
+    fn test_parse_fish_alias_output() {
+        let defs = parse_definitions(&lines("alias ll 'ls -la'\nabbr -a -- gco 'git checkout'"), "-");
+        assert_eq!(defs[0].name, "ll");
+        assert_eq!(defs[0].expansion, "ls -la");
+        assert_eq!(defs[1].name, "gco");
+        assert_eq!(defs[1].expansion, "git checkout");
+    }
+
+    #[test]
+    fn test_expand_command() {
+        let defs = parse_definitions(&lines("alias gco='git checkout'\nalias ls='ls --color'\nalias l='ls -l'"), "rc");
+        assert_eq!(expand_command("gco main", &defs), "git checkout main");
+        assert_eq!(expand_command("l && gco -b x | less", &defs), "ls --color -l && git checkout -b x | less");
+        assert_eq!(expand_command("  gco", &defs), "  git checkout");
+        assert_eq!(expand_command("echo gco", &defs), "echo gco");
+        // Quoted words and redirections are not command positions
+        assert_eq!(expand_command(r#"echo "l; gco""#, &defs), r#"echo "l; gco""#);
+        assert_eq!(expand_command("'gco' x", &defs), "'gco' x");
+        assert_eq!(expand_command("make 2>&1 | l", &defs), "make 2>&1 | ls --color -l");
+        assert_eq!(expand_command("LANG=C l", &defs), "LANG=C ls --color -l");
+    }
+
+    #[test]
+    fn test_alias_audit() {
+        use chrono::TimeZone;
+        let defs = parse_definitions(&lines("alias gco='git checkout'\nalias unused='make clean'\nabbr -a gp git push"), "rc");
+        let ts = Local.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap();
+        let history: Vec<HistoryEntry> = ["gco main", "git checkout dev", "ls | gco", "git push origin"]
+            .iter()
+            .map(|c| HistoryEntry { timestamp: Some(ts), command: c.to_string(), session_id: None, ..Default::default() })
+            .collect();
+        let usage = alias_audit(&history, &defs);
+        let gco = usage.iter().find(|u| u.definition.name == "gco").unwrap();
+        assert_eq!(gco.uses, 2);
+        assert_eq!(gco.typed_out, 1);
+        assert_eq!(gco.last_used, Some(ts));
+        let gp = usage.iter().find(|u| u.definition.name == "gp").unwrap();
+        assert_eq!(gp.effective_uses(), 1);
+        assert_eq!(usage.last().unwrap().definition.name, "unused");
+        assert_eq!(usage.last().unwrap().effective_uses(), 0);
+        print_alias_audit(&usage);
+    }
+
+    #[test]
+    fn test_mnemonic_names() {
+        assert_eq!(mnemonic("git commit -m 'wip'"), "gcm");
+        assert_eq!(mnemonic("docker compose up -d"), "dcud");
+        assert_eq!(mnemonic("ls -la /var/log/nginx"), "lla");
+        assert_eq!(mnemonic("./scripts/deploy.sh --force prod"), "df");
+        assert_eq!(mnemonic("git commit -m 'wip' -a"), "gcma");
+    }
+
+    #[test]
+    fn test_alias_suggestions_avoid_collisions() {
+        let defs = parse_definitions(&lines("alias gcm='git checkout main'"), "rc");
+        let history: Vec<HistoryEntry> = ["git commit -m 'wip' -a", "git commit -m 'wip' -a", "git checkout main", "git clone --mirror x"]
+            .iter()
+            .map(|c| HistoryEntry { timestamp: None, command: c.to_string(), session_id: None, ..Default::default() })
+            .collect();
+        let reserved: HashSet<String> = ["gcma".to_string(), "gcma2".to_string()].into_iter().collect();
+        let suggestions = alias_suggestions(&history, &defs, &reserved);
+        assert_eq!(suggestions.len(), 2);
+        assert_eq!(suggestions[0].name, "gcma3");
+        assert_eq!(suggestions[0].count, 2);
+        // `gcm` is already an alias
+        assert_eq!(suggestions[1].name, "gcm2");
+    }
+
+    #[test]
+    fn test_format_alias_syntax() {
+        let cmd = "git commit -m 'x'";
+        assert_eq!(format_alias("gcm", cmd, AliasSyntax::Bash), r"alias gcm='git commit -m '\''x'\'''");
+        assert_eq!(format_alias("gcm", cmd, AliasSyntax::Fish), r"abbr -a gcm 'git commit -m \'x\''");
+        assert_eq!(format_alias("gcm", cmd, AliasSyntax::Nu), "alias gcm = git commit -m 'x'");
+    }
+}
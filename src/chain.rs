@@ -0,0 +1,193 @@
+//! Hash-chained live history: with `HEIST_LIVE_CHAIN=1`, live tracking adds `chain=<sha256>`
+//! as the last metadata field of each record, hashed over the previous record's hash, a newline
+//! and the record without that field. `heist verify` recomputes the chain and reports the first
+//! record where it breaks.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use sha2::{Digest, Sha256};
+
+use crate::cli::VerifyArgs;
+use crate::rewrite::{FileFormat, HistoryFile, Record};
+
+/// Previous hash of the first chained record
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Hash of a record given the hash of the one before it
+pub fn link_hash(prev: &str, content: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(prev.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(content);
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Split a live record into the bytes that were hashed and its `chain=` hash, if it has one
+pub fn split_chain(line: &[u8]) -> Option<(Vec<u8>, String)> {
+    let ts_end = line.iter().position(|&b| b == b'|')? + 1;
+    let meta_part = line[ts_end..].strip_prefix(b"@")?;
+    let meta_end = meta_part.iter().position(|&b| b == b'|')?;
+    let meta = &meta_part[..meta_end];
+    let (kept, field) = match meta.iter().rposition(|&b| b == b',') {
+        Some(i) => (&meta[..i], &meta[i + 1..]),
+        None => (&meta[..0], meta),
+    };
+    let hash = field.strip_prefix(b"chain=")?;
+    let mut content = line[..ts_end].to_vec();
+    content.push(b'@');
+    content.extend_from_slice(kept);
+    content.extend_from_slice(&meta_part[meta_end..]);
+    Some((content, String::from_utf8_lossy(hash).into_owned()))
+}
+
+/// Whether any record of a live file carries a hash
+pub fn is_chained(file: &HistoryFile) -> bool {
+    file.records.iter().any(|r| r.lines.first().is_some_and(|l| split_chain(l).is_some()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakKind {
+    /// The stored hash does not match the record and the hash before it
+    Mismatch,
+    /// A record without a hash after the chain started
+    Unchained,
+}
+
+impl BreakKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            BreakKind::Mismatch => "hash does not match (this entry was edited, or the one before it was edited, removed or inserted)",
+            BreakKind::Unchained => "entry has no hash (inserted, or written with HEIST_LIVE_CHAIN off)",
+        }
+    }
+}
+
+/// The first record where the chain breaks
+#[derive(Debug, Clone)]
+pub struct BrokenLink {
+    pub line: usize,
+    pub kind: BreakKind,
+    pub record: Record,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainReport {
+    /// Non-empty records in the file
+    pub records: usize,
+    /// Records without a hash before the chain starts (written before chaining was enabled)
+    pub unchained_before: usize,
+    /// Records whose hash was verified
+    pub verified: usize,
+    /// Line and hash of the last verified record; keep it elsewhere to detect later truncation
+    pub head: Option<(usize, String)>,
+    pub broken: Option<BrokenLink>,
+}
+
+/// Walk the chain of a live history file up to its first broken link
+pub fn verify_chain(file: &HistoryFile) -> ChainReport {
+    let mut report = ChainReport { records: 0, unchained_before: 0, verified: 0, head: None, broken: None };
+    for record in &file.records {
+        let Some(line) = record.lines.first().filter(|l| !l.iter().all(u8::is_ascii_whitespace)) else { continue };
+        report.records += 1;
+        if report.broken.is_some() {
+            continue;
+        }
+        let kind = match (split_chain(line), &report.head) {
+            (None, None) => {
+                report.unchained_before += 1;
+                continue;
+            },
+            (None, Some(_)) => BreakKind::Unchained,
+            (Some((content, hash)), head) => {
+                let prev = head.as_ref().map_or(GENESIS, |(_, h)| h.as_str());
+                if link_hash(prev, &content) == hash {
+                    report.verified += 1;
+                    report.head = Some((record.line, hash));
+                    continue;
+                }
+                BreakKind::Mismatch
+            },
+        };
+        report.broken = Some(BrokenLink { line: record.line, kind, record: record.clone() });
+    }
+    report
+}
+
+/// Run `heist verify`; fails when the chain is broken, so scripts can check the exit status
+pub fn run_verify(args: &VerifyArgs) -> Result<()> {
+    let path = match args.file {
+        Some(ref file) => PathBuf::from(file),
+        None => crate::parser::live_history_path()?,
+    };
+    let home = crate::cwd::home_dir_string();
+    let name = crate::cwd::display_dir(&path.to_string_lossy(), &home);
+    let file = HistoryFile::read(&path, FileFormat::Live)?;
+    let report = verify_chain(&file);
+    if report.verified == 0 && report.broken.is_none() {
+        println!("{}: no hash-chained entries. Set HEIST_LIVE_CHAIN=1 before sourcing heist_live_tracking.sh to start a chain.", name);
+        return Ok(());
+    }
+    println!("{}: {} entries, {} verified", name, report.records, report.verified);
+    if report.unchained_before > 0 {
+        println!("  {} older entries without a hash precede the chain", report.unchained_before);
+    }
+    if let Some((line, ref hash)) = report.head {
+        println!("  Last verified entry: line {}, hash {}", line, hash);
+    }
+    match report.broken {
+        Some(ref broken) => {
+            println!("\nChain broken at line {}: {}", broken.line, broken.kind.label());
+            for line in broken.record.display_lines(FileFormat::Live) {
+                println!("  {}", line);
+            }
+            bail!("Hash chain of {} is broken at line {}", name, broken.line)
+        },
+        None => {
+            println!("\nChain intact. Removing the newest entries leaves a valid chain; compare the last hash with one recorded earlier to rule that out.");
+            Ok(())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Append a record the way heist_live_tracking.sh does
+    fn append(text: &mut String, prev: &str, ts: &str, meta: &str, cmd: &str) -> String {
+        let hash = link_hash(prev, format!("{}|@{}|{}", ts, meta, cmd).as_bytes());
+        text.push_str(&format!("{}|@{},chain={}|{}\n", ts, meta, hash, cmd));
+        hash
+    }
+
+    fn verify(text: &str) -> ChainReport {
+        verify_chain(&HistoryFile::from_bytes(Path::new("h"), FileFormat::Live, text.as_bytes()))
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        // Same value as `printf '%s\n%s' "$GENESIS" '2024-06-09T12:34:56+0000|@exit=0|ls' | sha256sum`
+        assert_eq!(
+            link_hash(GENESIS, b"2024-06-09T12:34:56+0000|@exit=0|ls"),
+            "599a6822c511e4f184008028b213783f2eccc5bdf3c890e869c38d86b47fcbad"
+        );
+        let mut text = "2024-06-09T12:00:00+0000|old entry\n".to_string();
+        let h1 = append(&mut text, GENESIS, "2024-06-09T12:34:56+0000", "exit=0", "ls");
+        let h2 = append(&mut text, &h1, "2024-06-09T12:35:00+0000", "exit=1,cwd=/srv", "make|tee log");
+        append(&mut text, &h2, "2024-06-09T12:36:00+0000", "exit=0", "echo ,chain=x");
+        let report = verify(&text);
+        assert_eq!((report.records, report.unchained_before, report.verified), (4, 1, 3));
+        assert!(report.broken.is_none());
+
+        let edited = text.replace("make|tee", "make |tee");
+        let broken = verify(&edited).broken.unwrap();
+        assert_eq!((broken.line, broken.kind), (3, BreakKind::Mismatch));
+        let lines: Vec<&str> = text.lines().collect();
+        let removed = [lines[0], lines[1], lines[3]].join("\n");
+        assert_eq!(verify(&removed).broken.unwrap().line, 3);
+        let inserted = format!("{}2024-06-09T12:37:00+0000|rm -rf /\n", text);
+        assert_eq!(verify(&inserted).broken.unwrap().kind, BreakKind::Unchained);
+    }
+}
@@ -1,5 +1,5 @@
 //! CLI argument parsing using clap
-use clap::{Parser, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug, Clone)]
 #[command(name = "heist", about = "Cross-platform shell history analyzer")]
@@ -12,6 +12,10 @@ pub struct CliArgs {
     #[arg(long, value_name = "N")]
     pub top: Option<usize>,
 
+    /// Subcommand depth for top commands (1 = program only, 2 = `git commit`, 3 = `kubectl get pods`)
+    #[arg(long, value_name = "D")]
+    pub depth: Option<usize>,
+
     /// Search for commands using regex
     #[arg(long, value_name = "PATTERN")]
     pub search: Option<String>,
@@ -24,10 +28,26 @@ pub struct CliArgs {
     #[arg(long, value_name = "RANGE")]
     pub range: Option<String>,
 
-    /// Export data to CSV or JSON
+    /// Only analyze commands that failed or succeeded (needs recorded exit codes)
+    #[arg(long, value_enum, value_name = "STATUS")]
+    pub status: Option<StatusFilter>,
+
+    /// Export data to CSV or JSON, or a forensic timeline as a mactime `bodyfile` or Plaso l2tcsv (`plaso`)
     #[arg(long, value_name = "FORMAT")]
     pub export: Option<String>,
 
+    /// Export commands verbatim instead of masking secrets, IPs, hostnames and home paths
+    #[arg(long, conflicts_with = "redact")]
+    pub no_redact: bool,
+
+    /// Also mask secrets, IPs, hostnames and home paths in the TUI and printed reports
+    #[arg(long)]
+    pub redact: bool,
+
+    /// Extra regex to mask as `[REDACTED]` (repeatable; also read from ~/.config/heist/redact_patterns)
+    #[arg(long, value_name = "REGEX")]
+    pub redact_pattern: Vec<String>,
+
     /// Force shell type (bash, zsh, fish)
     #[arg(long, value_enum, value_name = "SHELL")]
     pub shell: Option<ShellType>,
@@ -40,14 +60,99 @@ pub struct CliArgs {
     #[arg(long)]
     pub suggest_aliases: bool,
 
-    /// Flag potentially dangerous commands in history
+    /// Suggest shell functions for commands that differ only in a few arguments
+    #[arg(long)]
+    pub suggest_functions: bool,
+
+    /// Syntax for suggested aliases and functions: bash/zsh `alias`, fish `abbr` or nushell `alias` (default: detected shell)
+    #[arg(long, value_enum, value_name = "SHELL")]
+    pub alias_shell: Option<AliasSyntax>,
+
+    /// Write suggested aliases or functions to FILE so it can be sourced
+    #[arg(long, value_name = "FILE")]
+    pub alias_output: Option<String>,
+
+    /// Count alias/function-expanded commands (`gco main` → `git checkout main`)
+    #[arg(long)]
+    pub expand_aliases: bool,
+
+    /// Read alias/function definitions from FILE (`-` for `alias` output on stdin) instead of rc files
+    #[arg(long, value_name = "FILE")]
+    pub aliases_from: Option<String>,
+
+    /// List defined aliases/abbreviations/functions with usage, flagging unused ones and typed-out expansions
+    #[arg(long)]
+    pub alias_audit: bool,
+
+    /// Find likely typos (`gti status`, `cd..`) and suggest correction aliases (honours --alias-shell/--alias-output)
+    #[arg(long)]
+    pub typos: bool,
+
+    /// Infer failures from retries (re-runs, `sudo` re-runs, changed flags) without exit codes
+    #[arg(long)]
+    pub retries: bool,
+
+    /// Show failure rates, exit codes, failure streaks and fixes from recorded exit statuses
+    #[arg(long)]
+    pub exit_status: bool,
+
+    /// Show slowest commands, time per tool, weekly trends and duration outliers (combine with --export json)
+    #[arg(long)]
+    pub durations: bool,
+
+    /// Audit commands run through sudo, doas, su -c and pkexec: wrapped commands, root shells, escalating sessions (combine with --export json)
+    #[arg(long)]
+    pub privilege: bool,
+
+    /// Check for history tampering: clearing commands, gaps, shrunk or re-dated files, native vs live disagreements (combine with --export json)
+    #[arg(long)]
+    pub tampering: bool,
+
+    /// Minimum gap in hours reported by --tampering (raised to the usual longest breaks for long histories)
+    #[arg(long, value_name = "HOURS", default_value_t = 24.0)]
+    pub min_gap: f64,
+
+    /// Save history file sizes and fingerprints for the next --tampering run to compare against
+    #[arg(long, requires = "tampering")]
+    pub save_state: bool,
+
+    /// Scan history for secrets and credentials (values are masked; combine with --export json)
+    #[arg(long)]
+    pub secrets: bool,
+
+    /// Flag potentially dangerous commands in history, with severity and remediation (combine with --export json)
     #[arg(long)]
     pub flag_dangerous: bool,
 
+    /// Dangerous-command rules file (TOML) merged over the built-in rules [default: ~/.config/heist/rules.toml]
+    #[arg(long, value_name = "FILE")]
+    pub rules: Option<String>,
+
+    /// Mine frequent command sequences across sessions
+    #[arg(long)]
+    pub workflows: bool,
+
+    /// Minimum number of sessions a workflow must appear in
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub min_support: usize,
+
+    /// Minimum share of sessions starting a workflow that complete it (0.0-1.0)
+    #[arg(long, value_name = "F", default_value_t = 0.5)]
+    pub min_confidence: f64,
+
+    /// Generate a script for workflow N from --workflows (a function with --alias-shell)
+    #[arg(long, value_name = "N")]
+    pub workflow_script: Option<usize>,
+
     /// Show per-directory command stats
     #[arg(long)]
     pub per_directory: bool,
-    /// Show per-host command stats
+
+    /// Show time spent, top commands, builds/tests and active days per project (combine with --export json)
+    #[arg(long)]
+    pub projects: bool,
+
+    /// Show commands, top commands and host-only commands per host (combine with --export json)
     #[arg(long)]
     pub per_host: bool,
 
@@ -57,6 +162,152 @@ pub struct CliArgs {
     /// Show weekly heatmap of command usage
     #[arg(long)]
     pub heatmap: bool,
+
+    /// Show pipeline and command-chain analytics (combine with --export json)
+    #[arg(long)]
+    pub pipelines: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Print likely next commands from recent history and the working directory
+    Predict(PredictArgs),
+    /// Mask or remove secrets in the history files themselves (prints a diff; rewrites with --apply)
+    Scrub(ScrubArgs),
+    /// Delete history entries matching a regex from the history files
+    Delete(DeleteArgs),
+    /// Remove trivial commands (`ls`, `cd`, `clear`, ... without arguments) from the history files
+    Prune(PruneArgs),
+    /// Collapse duplicate entries in the history files, keeping the newest copy
+    Dedup(RewriteTarget),
+    /// Roll history files back to a backup taken by scrub, delete, prune or dedup
+    Restore(RestoreArgs),
+    /// Check the hash chain of the live history (HEIST_LIVE_CHAIN=1) and report the first broken link
+    Verify(VerifyArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct PredictArgs {
+    /// Previous command of the session, oldest first (repeatable; default: end of history)
+    #[arg(long, value_name = "CMD")]
+    pub previous: Vec<String>,
+
+    /// Working directory to predict for (default: current directory)
+    #[arg(long, value_name = "DIR")]
+    pub cwd: Option<String>,
+
+    /// Only suggest commands starting with the typed text
+    #[arg(long, value_name = "TEXT")]
+    pub prefix: Option<String>,
+
+    /// Number of suggestions
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 5)]
+    pub limit: usize,
+
+    /// Print the score of each suggestion
+    #[arg(long)]
+    pub scores: bool,
+
+    /// Measure accuracy on the newest part of history instead of predicting
+    #[arg(long)]
+    pub evaluate: bool,
+
+    /// Share of history held out for --evaluate
+    #[arg(long, value_name = "F", default_value_t = 0.2)]
+    pub holdout: f64,
+}
+
+/// Which history files to rewrite, and whether to write them
+#[derive(Args, Debug, Clone)]
+pub struct RewriteTarget {
+    /// Rewrite the files (after a backup to ~/.heist_backups) instead of only printing the diff
+    #[arg(long)]
+    pub apply: bool,
+
+    /// Rewrite this file instead of the shell's history and the live log (format guessed from the name)
+    #[arg(long, value_name = "FILE")]
+    pub file: Option<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ScrubArgs {
+    #[command(flatten)]
+    pub target: RewriteTarget,
+
+    /// Delete entries containing secrets instead of masking the values
+    #[arg(long)]
+    pub remove: bool,
+
+    /// Only scrub secrets found with at least this confidence
+    #[arg(long, value_enum, value_name = "LEVEL", default_value = "low")]
+    pub min_confidence: crate::secrets::Confidence,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct DeleteArgs {
+    /// Regex matched against each command
+    #[arg(value_name = "PATTERN")]
+    pub pattern: String,
+
+    #[command(flatten)]
+    pub target: RewriteTarget,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct PruneArgs {
+    /// Comma-separated programs to prune when run without arguments (default: ls, ll, la, l, cd, pwd, clear, reset, exit, history)
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    pub commands: Vec<String>,
+
+    #[command(flatten)]
+    pub target: RewriteTarget,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RestoreArgs {
+    /// Backup to restore, as named by --list (default: the newest)
+    #[arg(value_name = "BACKUP")]
+    pub backup: Option<String>,
+
+    /// List backups instead of restoring
+    #[arg(long)]
+    pub list: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct VerifyArgs {
+    /// Live history file to verify (default: ~/.heist_live_history)
+    #[arg(long, value_name = "PATH")]
+    pub file: Option<String>,
+}
+
+/// Exit-status filter
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum StatusFilter {
+    Failed,
+    Succeeded,
+}
+
+impl StatusFilter {
+    /// Whether an entry passes the filter; entries without a recorded status never do
+    pub fn matches(&self, entry: &crate::models::HistoryEntry) -> bool {
+        match self {
+            StatusFilter::Failed => entry.failed() == Some(true),
+            StatusFilter::Succeeded => entry.failed() == Some(false),
+        }
+    }
+}
+
+/// Output syntax for alias suggestions
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum AliasSyntax {
+    Bash,
+    Zsh,
+    Fish,
+    Nu,
 }
 
 #[derive(ValueEnum, Debug, Clone)]
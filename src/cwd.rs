@@ -0,0 +1,247 @@
+//! Working-directory reconstruction: replays `cd`, `pushd` and `popd` within each session
+//! Recorded directories (live tracker) take precedence over reconstructed ones
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::models::HistoryEntry;
+use crate::tokenizer::pipelines;
+
+/// Gap that starts a new shell session, which is assumed to open in the home directory
+const SESSION_GAP_MINUTES: i64 = 10;
+
+/// Home directory as an absolute path string (`~` if it cannot be determined)
+pub fn home_dir_string() -> String {
+    dirs::home_dir()
+        .map(|h| h.to_string_lossy().trim_end_matches('/').to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "~".to_string())
+}
+
+/// Shorten `home` to `~` for display
+pub fn display_dir(path: &str, home: &str) -> String {
+    match path.strip_prefix(home) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("~{}", rest),
+        _ => path.to_string(),
+    }
+}
+
+/// Resolve `target` against `cwd`, collapsing `.` and `..`. `None` when the result is unknown.
+pub fn resolve_path(cwd: Option<&str>, target: &str, home: &str) -> Option<String> {
+    // Variables, substitutions, globs and `~user` cannot be resolved from history alone
+    if target.contains(['$', '`', '*', '?', '[']) || (target.starts_with('~') && target != "~" && !target.starts_with("~/")) {
+        return None;
+    }
+    let base = if target.starts_with('/') {
+        String::new()
+    } else if let Some(rest) = target.strip_prefix('~') {
+        return resolve_path(Some(home), rest.trim_start_matches('/'), home);
+    } else {
+        cwd?.to_string()
+    };
+    let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+    for part in target.split('/') {
+        match part {
+            "" | "." => {},
+            ".." => { parts.pop(); },
+            p => parts.push(p),
+        }
+    }
+    Some(format!("/{}", parts.join("/")))
+}
+
+/// Shell directory state: current directory, `$OLDPWD` and the `pushd` stack
+#[derive(Debug, Clone)]
+pub struct CwdTracker {
+    home: String,
+    cwd: Option<String>,
+    oldpwd: Option<String>,
+    stack: Vec<Option<String>>,
+}
+
+impl CwdTracker {
+    /// A new shell starting in the home directory
+    pub fn new(home: &str) -> Self {
+        CwdTracker { home: home.to_string(), cwd: Some(home.to_string()), oldpwd: None, stack: vec![] }
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.cwd.as_deref()
+    }
+
+    /// Use a directory recorded by the history source
+    pub fn set(&mut self, dir: &str) {
+        self.cwd = Some(dir.to_string());
+    }
+
+    fn change_to(&mut self, dir: Option<String>) {
+        self.oldpwd = std::mem::replace(&mut self.cwd, dir);
+    }
+
+    /// Apply the directory changes of a command line, including `cd` inside `&&`/`;` chains.
+    /// Single-stage commands only: `cd x | cat` runs in a subshell.
+    pub fn observe(&mut self, command: &str) {
+        for chain in pipelines(command) {
+            let [stage] = chain.as_slice() else { continue };
+            let Some(program) = stage.first() else { continue };
+            let args: Vec<&str> = stage[1..].iter().map(|s| s.as_str()).filter(|a| !matches!(*a, "-L" | "-P" | "-e" | "--")).collect();
+            match (program.as_str(), args.as_slice()) {
+                ("cd", []) | ("cd", ["~"]) => self.change_to(Some(self.home.clone())),
+                ("cd", ["-"]) => {
+                    let target = self.oldpwd.clone();
+                    self.change_to(target);
+                },
+                ("cd", [target]) => {
+                    let target = resolve_path(self.cwd.as_deref(), target, &self.home);
+                    self.change_to(target);
+                },
+                ("pushd", []) => {
+                    // Swap the top two directories
+                    if let Some(top) = self.stack.pop() {
+                        let current = self.cwd.take();
+                        self.stack.push(current);
+                        self.cwd = top;
+                    }
+                },
+                ("pushd", [target]) if !target.starts_with(['+', '-']) => {
+                    let target = resolve_path(self.cwd.as_deref(), target, &self.home);
+                    self.stack.push(self.cwd.clone());
+                    self.change_to(target);
+                },
+                ("popd", []) => {
+                    if let Some(top) = self.stack.pop() {
+                        self.change_to(top);
+                    }
+                },
+                ("cd", _) | ("pushd", _) | ("popd", _) => self.change_to(None),
+                _ => {},
+            }
+        }
+    }
+}
+
+/// Directory each command was started in, aligned with `history`. Sessions restart in `home`;
+/// a recorded cwd wins over the reconstruction, and failed commands do not change directory.
+pub fn resolve_cwds(history: &[HistoryEntry], home: &str) -> Vec<Option<String>> {
+    let mut tracker = CwdTracker::new(home);
+    let mut last_ts: Option<chrono::DateTime<chrono::Local>> = None;
+    history.iter().map(|entry| {
+        if let (Some(ts), Some(last)) = (entry.timestamp, last_ts) {
+            if ts.signed_duration_since(last).num_minutes() > SESSION_GAP_MINUTES {
+                tracker = CwdTracker::new(home);
+            }
+        }
+        last_ts = entry.timestamp.or(last_ts);
+        if let Some(ref dir) = entry.cwd {
+            tracker.set(dir);
+        }
+        let cwd = tracker.current().map(|d| d.to_string());
+        if entry.failed() != Some(true) {
+            tracker.observe(&entry.command);
+        }
+        cwd
+    }).collect()
+}
+
+/// Commands run in a directory, and in the directory including its subdirectories
+#[derive(Debug, Clone, Serialize)]
+pub struct DirStats {
+    pub dir: String,
+    pub commands: usize,
+    pub subtree: usize,
+}
+
+/// Per-directory counts from reconstructed working directories, sorted by commands run directly
+/// in the directory. Entries whose directory cannot be determined are reported as `(unknown)`.
+pub fn directory_stats(history: &[HistoryEntry], home: &str) -> Vec<DirStats> {
+    let mut own: HashMap<String, usize> = HashMap::new();
+    for cwd in resolve_cwds(history, home) {
+        *own.entry(cwd.unwrap_or_else(|| "(unknown)".to_string())).or_insert(0) += 1;
+    }
+    let mut subtree: HashMap<&str, usize> = HashMap::new();
+    for (dir, count) in &own {
+        if !dir.starts_with('/') {
+            *subtree.entry(dir).or_insert(0) += count;
+            continue;
+        }
+        // Credit the directory and every ancestor
+        let mut end = dir.trim_end_matches('/').len();
+        loop {
+            *subtree.entry(if end == 0 { "/" } else { &dir[..end] }).or_insert(0) += count;
+            if end == 0 {
+                break;
+            }
+            end = dir[..end].rfind('/').unwrap_or(0);
+        }
+    }
+    let mut stats: Vec<DirStats> = own.iter()
+        .map(|(dir, count)| DirStats { dir: dir.clone(), commands: *count, subtree: subtree.get(dir.as_str()).copied().unwrap_or(*count) })
+        .collect();
+    stats.sort_by(|a, b| b.commands.cmp(&a.commands).then_with(|| a.dir.cmp(&b.dir)));
+    stats
+}
+
+/// Print the 15 busiest directories
+pub fn print_directory_stats(stats: &[DirStats], home: &str) {
+    println!("\nPer-directory command stats:");
+    println!("{:<40} {:>8} {:>8}", "Directory", "Commands", "Subtree");
+    for s in stats.iter().take(15) {
+        println!("{:<40} {:>8} {:>8}", display_dir(&s.dir, home), s.commands, s.subtree);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOME: &str = "/home/u";
+
+    #[test]
+    fn test_tracker_cd_variants() {
+        let mut t = CwdTracker::new(HOME);
+        t.observe("cd src/bin");
+        t.observe("cd ..");
+        assert_eq!(t.current(), Some("/home/u/src"));
+        t.observe("cd /etc && ls");
+        assert_eq!(t.current(), Some("/etc"));
+        t.observe("cd -");
+        assert_eq!(t.current(), Some("/home/u/src"));
+        t.observe("pushd /var/log");
+        t.observe("cd nginx");
+        t.observe("popd");
+        assert_eq!(t.current(), Some("/home/u/src"));
+        t.observe("cd ~/x; cd ../y");
+        assert_eq!(t.current(), Some("/home/u/y"));
+        t.observe("cd /tmp | cat");
+        assert_eq!(t.current(), Some("/home/u/y"));
+        t.observe("cd $PROJECT");
+        assert_eq!(t.current(), None);
+        t.observe("cd");
+        assert_eq!(t.current(), Some(HOME));
+    }
+
+    #[test]
+    fn test_resolve_cwds_and_stats() {
+        let entry = |command: &str, cwd: Option<&str>| HistoryEntry { command: command.to_string(), cwd: cwd.map(String::from), ..Default::default() };
+        let history = vec![
+            entry("cd proj", None),
+            entry("make", None),
+            entry("cd src", None),
+            entry("vim main.c", None),
+            entry("ls", Some("/srv")),
+            entry("cd ..", None),
+            entry("pwd", None),
+        ];
+        let cwds = resolve_cwds(&history, HOME);
+        assert_eq!(cwds[1].as_deref(), Some("/home/u/proj"));
+        assert_eq!(cwds[3].as_deref(), Some("/home/u/proj/src"));
+        assert_eq!(cwds[4].as_deref(), Some("/srv"));
+        assert_eq!(cwds[6].as_deref(), Some("/"));
+        let stats = directory_stats(&history, HOME);
+        let proj = stats.iter().find(|s| s.dir == "/home/u/proj").unwrap();
+        assert_eq!((proj.commands, proj.subtree), (2, 3));
+        assert_eq!(display_dir("/home/u/proj", HOME), "~/proj");
+        assert_eq!(display_dir("/home/user", HOME), "/home/user");
+    }
+}
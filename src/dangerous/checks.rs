@@ -0,0 +1,385 @@
+//! Semantic checks: inspect the parsed arguments of a pipeline stage instead of matching text,
+//! so that `rm -rf ./build` passes while `rm -rf $DIR/` does not. Each check returns the
+//! severity and a reason specific to the command.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use super::{has_flag, unwrap_stage, Severity};
+
+/// A built-in check, referenced from rules by name (`check = "rm"`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    Rm,
+    Dd,
+    Permissions,
+    GitPush,
+    KubectlDelete,
+    Sql,
+}
+
+pub type Finding = (Severity, String);
+
+impl Check {
+    pub const NAMES: [&'static str; 6] = ["rm", "dd", "permissions", "git-push", "kubectl-delete", "sql"];
+
+    pub fn from_name(name: &str) -> Option<Check> {
+        match name {
+            "rm" => Some(Check::Rm),
+            "dd" => Some(Check::Dd),
+            "permissions" => Some(Check::Permissions),
+            "git-push" => Some(Check::GitPush),
+            "kubectl-delete" => Some(Check::KubectlDelete),
+            "sql" => Some(Check::Sql),
+            _ => None,
+        }
+    }
+
+    /// Run the check on stage `i` of a pipeline
+    pub fn run(self, chain: &[Vec<String>], i: usize) -> Option<Finding> {
+        let stage = unwrap_stage(&chain[i]);
+        let (name, args) = stage.split_first()?;
+        let program = name.rsplit('/').next().unwrap_or(name);
+        match (self, program) {
+            (Check::Rm, "rm") => rm(args),
+            (Check::Dd, "dd") => dd(args),
+            (Check::Permissions, "chmod" | "chown" | "chgrp") => permissions(program, args),
+            (Check::GitPush, "git") => git_push(args),
+            (Check::KubectlDelete, "kubectl" | "oc") => kubectl_delete(args),
+            (Check::Sql, "psql" | "mysql" | "mariadb" | "sqlite3" | "sqlcmd" | "clickhouse-client") => {
+                let piped = i.checked_sub(1).map(|p| unwrap_stage(&chain[p])).filter(|s| matches!(s.first().map(|p| p.as_str()), Some("echo" | "printf")));
+                sql(program, args, piped)
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Non-flag arguments, honouring `--`
+fn operands(args: &[String]) -> Vec<&str> {
+    let mut out = vec![];
+    let mut after_dashes = false;
+    for a in args {
+        if a == "--" && !after_dashes {
+            after_dashes = true;
+        } else if after_dashes || !a.starts_with('-') || a == "-" {
+            out.push(a.as_str());
+        }
+    }
+    out
+}
+
+/// Value of an option given as `-n x`, `-nx`, `--namespace x` or `--namespace=x`
+fn option_value<'a>(args: &'a [String], short: Option<&str>, long: &str) -> Option<&'a str> {
+    let mut iter = args.iter();
+    while let Some(a) = iter.next() {
+        if a == long || Some(a.as_str()) == short {
+            return iter.next().map(|v| v.as_str());
+        }
+        if let Some(v) = a.strip_prefix(long).and_then(|r| r.strip_prefix('=')) {
+            return Some(v);
+        }
+        if let Some(v) = short.and_then(|s| a.strip_prefix(s)).filter(|v| !v.is_empty() && !a.starts_with("--")) {
+            return Some(v);
+        }
+    }
+    None
+}
+
+fn worst(findings: impl IntoIterator<Item = Finding>) -> Option<Finding> {
+    findings.into_iter().max_by_key(|(severity, _)| *severity)
+}
+
+const SYSTEM_DIRS: [&str; 14] = ["bin", "boot", "dev", "etc", "home", "lib", "lib64", "opt", "root", "sbin", "srv", "sys", "usr", "var"];
+
+/// What a path argument refers to, as far as it can be told without expanding it
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    Root,
+    Home,
+    System(String),
+    /// Starts with a variable that may be empty (`$DIR/`), naming the variable
+    UnsetVariable(String),
+    Parent,
+    Everything,
+    Other,
+}
+
+fn classify_target(path: &str) -> Target {
+    let trimmed = path.trim_end_matches("/*").trim_end_matches('/');
+    let trimmed = trimmed.strip_suffix("/.").unwrap_or(trimmed);
+    match trimmed {
+        "" | "/" => return Target::Root,
+        "~" | "$HOME" | "${HOME}" => return Target::Home,
+        ".." | "../*" => return Target::Parent,
+        "*" | ".*" | "." => return Target::Everything,
+        _ => {},
+    }
+    if let Some(rest) = trimmed.strip_prefix('/') {
+        if SYSTEM_DIRS.contains(&rest) {
+            return Target::System(format!("/{}", rest));
+        }
+    }
+    static VAR: OnceLock<Regex> = OnceLock::new();
+    let var = VAR.get_or_init(|| Regex::new(r"^\$(?:\{(\w+)\}|(\w+))/").unwrap());
+    // `${DIR:?}/` aborts when DIR is unset, so it does not match
+    match var.captures(path) {
+        Some(cap) => {
+            let name = cap.get(1).or(cap.get(2)).map(|m| m.as_str()).unwrap_or_default();
+            if name == "HOME" { Target::Other } else { Target::UnsetVariable(name.to_string()) }
+        },
+        None => Target::Other,
+    }
+}
+
+fn rm(args: &[String]) -> Option<Finding> {
+    if !["-r", "-R", "--recursive"].iter().any(|f| has_flag(args, f)) {
+        return None;
+    }
+    let force = has_flag(args, "-f") || has_flag(args, "--force");
+    worst(operands(args).into_iter().filter_map(|target| match classify_target(target) {
+        Target::Root => Some((Severity::Critical, "recursively deletes the whole filesystem".to_string())),
+        Target::Home => Some((Severity::Critical, format!("recursively deletes your home directory ({})", target))),
+        Target::System(dir) => Some((Severity::Critical, format!("recursively deletes the system directory {}", dir))),
+        Target::UnsetVariable(name) => Some((Severity::High, format!(
+            "if ${} is empty or unset, `{}` becomes `{}`; use ${{{}:?}} to abort instead",
+            name, target, target.split_once('/').map(|(_, rest)| format!("/{}", rest)).unwrap_or_default(), name))),
+        Target::Parent => Some((Severity::High, "recursively deletes the parent directory".to_string())),
+        Target::Everything if force => Some((Severity::Medium, format!("deletes everything in the current directory (`{}`) without asking", target))),
+        _ => None,
+    }))
+}
+
+fn is_block_device(path: &str) -> bool {
+    static DEV: OnceLock<Regex> = OnceLock::new();
+    DEV.get_or_init(|| Regex::new(r"^/dev/(sd[a-z]+|hd[a-z]+|vd[a-z]+|xvd[a-z]+|nvme\d+n\d+|mmcblk\d+|r?disk\d+|dm-\d+|md\d+|mapper/\S+|disk/by-\S+)(p?\d+)?(s\d+)?$").unwrap())
+        .is_match(path)
+}
+
+fn dd(args: &[String]) -> Option<Finding> {
+    let operand = |key: &str| args.iter().find_map(|a| a.strip_prefix(key));
+    let output = operand("of=").filter(|of| is_block_device(of))?;
+    let reason = match operand("if=") {
+        Some(input @ ("/dev/zero" | "/dev/urandom" | "/dev/random")) => format!("wipes block device {} with {}", output, input),
+        Some(input) => format!("overwrites block device {} with {}", output, input),
+        None => format!("overwrites block device {}", output),
+    };
+    Some((Severity::Critical, reason))
+}
+
+/// Whether a chmod mode lets other users write (`777`, `o+w`, `a=rwx`); the sticky bit (`1777`) is fine
+fn world_writable(mode: &str) -> bool {
+    if mode.chars().all(|c| c.is_ascii_digit()) && !mode.is_empty() {
+        let digits: Vec<u32> = mode.chars().filter_map(|c| c.to_digit(8)).collect();
+        let sticky = digits.len() == 4 && digits[0] & 1 == 1;
+        return digits.last().is_some_and(|d| d & 2 == 2) && !sticky;
+    }
+    mode.split(',').any(|clause| {
+        let Some(pos) = clause.find(['+', '=']) else { return false };
+        let (who, perms) = clause.split_at(pos);
+        (who.contains(['o', 'a'])) && perms[1..].contains('w')
+    })
+}
+
+fn permissions(program: &str, args: &[String]) -> Option<Finding> {
+    let recursive = has_flag(args, "-R") || has_flag(args, "--recursive");
+    let ops = operands(args);
+    let (mode, targets) = ops.split_first()?;
+    let mut findings = vec![];
+    for target in targets {
+        let sensitive = matches!(classify_target(target), Target::Root | Target::Home | Target::System(_));
+        if program == "chmod" && world_writable(mode) {
+            findings.push(match (recursive, sensitive) {
+                (true, _) => (Severity::High, format!("makes every file under {} writable by all users", target)),
+                (false, true) => (Severity::High, format!("makes {} writable by all users", target)),
+                (false, false) => (Severity::Low, format!("makes {} writable by all users", target)),
+            });
+        }
+        if recursive && sensitive {
+            let what = if program == "chmod" { "permissions" } else { "ownership" };
+            findings.push((Severity::High, format!("recursively changes {} of everything under {}", what, target)));
+        }
+    }
+    worst(findings)
+}
+
+/// Branches whose history others rely on
+fn protected_branch(branch: &str) -> bool {
+    static PROTECTED: OnceLock<Regex> = OnceLock::new();
+    PROTECTED.get_or_init(|| Regex::new(r"^(main|master|trunk|develop|production|prod|release[/-].*|stable)$").unwrap()).is_match(branch)
+}
+
+fn git_push(args: &[String]) -> Option<Finding> {
+    // Skip global options: `git -C dir push`, `git -c key=value push`
+    let mut i = 0;
+    while i < args.len() && args[i].starts_with('-') {
+        i += if matches!(args[i].as_str(), "-C" | "-c") { 2 } else { 1 };
+    }
+    if args.get(i).map(|s| s.as_str()) != Some("push") {
+        return None;
+    }
+    let push = &args[i + 1..];
+    let refspecs: Vec<&str> = operands(push).into_iter().skip(1).collect();
+    let delete = has_flag(push, "-d") || has_flag(push, "--delete");
+    let branches: Vec<(bool, &str)> = refspecs.iter().map(|spec| {
+        let forced = spec.starts_with('+');
+        let dst = spec.trim_start_matches('+').rsplit(':').next().unwrap_or(spec);
+        (forced, dst.trim_start_matches("refs/heads/"))
+    }).collect();
+    let force = has_flag(push, "-f") || has_flag(push, "--force") || branches.iter().any(|(forced, _)| *forced);
+    let lease = push.iter().any(|a| a.starts_with("--force-with-lease"));
+    if delete || refspecs.iter().any(|s| s.starts_with(':')) {
+        if let Some((_, branch)) = branches.iter().find(|(_, b)| protected_branch(b)) {
+            return Some((Severity::High, format!("deletes the shared remote branch {}", branch)));
+        }
+        return None;
+    }
+    if !force && !lease {
+        return None;
+    }
+    match branches.iter().find(|(_, b)| protected_branch(b)) {
+        Some((_, branch)) if force => Some((Severity::High, format!("force-pushes over the history of the shared branch {}", branch))),
+        Some((_, branch)) => Some((Severity::Low, format!("force-pushes {} with a lease; others' pushes are kept, but their clones must rebase", branch))),
+        None if force && branches.is_empty() => Some((Severity::Medium, "force-pushes the current branch, which may be shared".to_string())),
+        None => None,
+    }
+}
+
+/// Options of kubectl that take a value, so their values are not mistaken for resources
+const KUBECTL_VALUE_FLAGS: [&str; 16] = [
+    "-n", "--namespace", "--context", "--cluster", "--kubeconfig", "--user", "-l", "--selector",
+    "-f", "--filename", "-o", "--output", "--grace-period", "--timeout", "--field-selector", "-c",
+];
+
+fn production(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    lower.split(['-', '_', '.', '/', ':']).any(|part| matches!(part, "prod" | "production" | "prd" | "live"))
+}
+
+fn kubectl_delete(args: &[String]) -> Option<Finding> {
+    let mut positional = vec![];
+    let mut iter = args.iter();
+    while let Some(a) = iter.next() {
+        if KUBECTL_VALUE_FLAGS.contains(&a.as_str()) {
+            iter.next();
+        } else if !a.starts_with('-') {
+            positional.push(a.as_str());
+        }
+    }
+    if positional.first() != Some(&"delete") {
+        return None;
+    }
+    let resource = positional.get(1).copied().unwrap_or("resources");
+    let name = positional.get(2).copied();
+    let namespace = option_value(args, Some("-n"), "--namespace");
+    let context = option_value(args, None, "--context");
+    let all_namespaces = has_flag(args, "-A") || has_flag(args, "--all-namespaces");
+    let all = has_flag(args, "--all");
+    let prod = namespace.into_iter().chain(context).find(|n| production(n));
+
+    if matches!(resource, "ns" | "namespace" | "namespaces") {
+        let ns = name.unwrap_or("namespaces");
+        return Some(match (production(ns) || prod.is_some(), all) {
+            (_, true) => (Severity::Critical, "deletes every namespace and everything in them".to_string()),
+            (true, _) => (Severity::Critical, format!("deletes the production namespace {} and everything in it", ns)),
+            _ => (Severity::High, format!("deletes namespace {} and everything in it", ns)),
+        });
+    }
+    if all_namespaces {
+        return Some((Severity::Critical, format!("deletes {} in every namespace", resource)));
+    }
+    if let Some(env) = prod {
+        let what = match name {
+            Some(name) if !all => format!("{} {}", resource, name),
+            _ => format!("every {}", resource),
+        };
+        return Some((if all { Severity::Critical } else { Severity::High }, format!("deletes {} in production ({})", what, env)));
+    }
+    if all {
+        return Some((Severity::Medium, format!("deletes every {} in {}", resource, namespace.map(|n| format!("namespace {}", n)).unwrap_or_else(|| "the current namespace".to_string()))));
+    }
+    if matches!(resource, "pv" | "persistentvolume" | "persistentvolumes" | "pvc" | "persistentvolumeclaim" | "persistentvolumeclaims") {
+        let name = name.map(|n| format!(" {}", n)).unwrap_or_default();
+        return Some((Severity::Medium, format!("deletes {}{}, which may delete the stored data", resource, name)));
+    }
+    None
+}
+
+/// Destructive statements: DROP, TRUNCATE, and DELETE/UPDATE without WHERE
+fn sql_statement(statement: &str) -> Option<Finding> {
+    static RE: OnceLock<[Regex; 6]> = OnceLock::new();
+    let [drop_db, drop_table, truncate, delete, update, where_clause] = RE.get_or_init(|| [
+        Regex::new(r"(?i)^\s*drop\s+(database|schema)\b").unwrap(),
+        Regex::new(r"(?i)^\s*drop\s+table\b").unwrap(),
+        Regex::new(r"(?i)^\s*truncate\b").unwrap(),
+        Regex::new(r"(?i)^\s*delete\s+from\b").unwrap(),
+        Regex::new(r"(?i)^\s*update\s+\S+\s+set\b").unwrap(),
+        Regex::new(r"(?i)\bwhere\b").unwrap(),
+    ]);
+    let text = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+    if drop_db.is_match(statement) {
+        Some((Severity::Critical, format!("`{}` removes a whole database", text)))
+    } else if drop_table.is_match(statement) || truncate.is_match(statement) {
+        Some((Severity::High, format!("`{}` permanently removes a table's data", text)))
+    } else if delete.is_match(statement) && !where_clause.is_match(statement) {
+        Some((Severity::High, format!("`{}` deletes every row (no WHERE clause)", text)))
+    } else if update.is_match(statement) && !where_clause.is_match(statement) {
+        Some((Severity::Medium, format!("`{}` updates every row (no WHERE clause)", text)))
+    } else {
+        None
+    }
+}
+
+fn sql(program: &str, args: &[String], piped: Option<&[String]>) -> Option<Finding> {
+    let mut scripts: Vec<&str> = [
+        (Some("-c"), "--command"),
+        (Some("-e"), "--execute"),
+        (Some("-q"), "--query"),
+        (Some("-Q"), "--query"),
+    ].iter().filter_map(|(short, long)| option_value(args, *short, long)).collect();
+    if program == "sqlite3" {
+        scripts.extend(operands(args).into_iter().skip(1));
+    }
+    if let Some(stage) = piped {
+        scripts.extend(stage[1..].iter().map(|s| s.as_str()).filter(|s| !s.starts_with('-')));
+    }
+    let (severity, reason) = worst(scripts.iter().flat_map(|script| script.split(';')).filter_map(sql_statement))?;
+    Some((severity, format!("{} via {}", reason, program)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::pipelines;
+
+    fn run(check: Check, command: &str) -> Option<Severity> {
+        let chains = pipelines(command);
+        chains.iter().flat_map(|chain| (0..chain.len()).filter_map(|i| check.run(chain, i))).map(|(s, _)| s).max()
+    }
+
+    #[test]
+    fn test_semantic_checks() {
+        assert_eq!(run(Check::Rm, "rm -rf ~"), Some(Severity::Critical));
+        assert_eq!(run(Check::Rm, "rm -rf \"$BUILD_DIR\"/"), Some(Severity::High));
+        assert_eq!(run(Check::Rm, "rm -rf ${BUILD_DIR:?}/"), None);
+        assert_eq!(run(Check::Rm, "rm -rf ./build node_modules"), None);
+        assert_eq!(run(Check::Rm, "rm -rf *"), Some(Severity::Medium));
+        assert_eq!(run(Check::Dd, "sudo dd if=/dev/zero of=/dev/nvme0n1 bs=1M"), Some(Severity::Critical));
+        assert_eq!(run(Check::Dd, "dd if=/dev/zero of=swap.img bs=1M count=512"), None);
+        assert_eq!(run(Check::Permissions, "chmod -R 777 ."), Some(Severity::High));
+        assert_eq!(run(Check::Permissions, "chmod -R 755 public"), None);
+        assert_eq!(run(Check::Permissions, "chmod 1777 /tmp/shared"), None);
+        assert_eq!(run(Check::GitPush, "git push --force origin main"), Some(Severity::High));
+        assert_eq!(run(Check::GitPush, "git push origin +HEAD:master"), Some(Severity::High));
+        assert_eq!(run(Check::GitPush, "git push -f origin feature/login"), None);
+        assert_eq!(run(Check::GitPush, "git push --force-with-lease origin main"), Some(Severity::Low));
+        assert_eq!(run(Check::KubectlDelete, "kubectl delete pod api-7d9 -n prod"), Some(Severity::High));
+        assert_eq!(run(Check::KubectlDelete, "kubectl -n dev delete pod api-7d9"), None);
+        assert_eq!(run(Check::KubectlDelete, "kubectl delete ns staging"), Some(Severity::High));
+        assert_eq!(run(Check::Sql, "psql -h db -c 'DROP TABLE users'"), Some(Severity::High));
+        assert_eq!(run(Check::Sql, "psql -c 'DELETE FROM users WHERE id = 4'"), None);
+        assert_eq!(run(Check::Sql, "mysql -e \"delete from sessions\""), Some(Severity::High));
+        assert_eq!(run(Check::Sql, "echo 'drop database app;' | mysql"), Some(Severity::Critical));
+    }
+}
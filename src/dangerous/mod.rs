@@ -0,0 +1,448 @@
+//! Dangerous-command rules engine shared by the CLI (`--flag-dangerous`) and the TUI
+//! Built-in rules can be overridden, disabled or extended from a TOML rules file
+
+mod checks;
+
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write as IoWrite;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::CliArgs;
+use crate::models::HistoryEntry;
+use crate::tokenizer::{is_assignment, pipelines};
+use checks::Check;
+
+// Logging macro for errors
+macro_rules! log_error {
+    ($($arg:tt)*) => {{
+        let msg = format!($($arg)*);
+        eprintln!("[heist error] {}", msg);
+        if let Ok(mut f) = OpenOptions::new().create(true).append(true).open("heist_error.log") {
+            let _ = writeln!(f, "{}", msg);
+        }
+    }};
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Low => "LOW",
+            Severity::Medium => "MEDIUM",
+            Severity::High => "HIGH",
+            Severity::Critical => "CRITICAL",
+        }
+    }
+}
+
+/// A rule as written in the rules file. At least one of `pattern` (regex over the whole command
+/// line), `program` (token rule over each pipeline stage) or `check` (built-in semantic check)
+/// must be set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleDef {
+    pub id: String,
+    pub severity: Option<Severity>,
+    pub category: String,
+    pub explanation: String,
+    pub remediation: String,
+    pub pattern: Option<String>,
+    /// Regex the stage's program must match in full, after `sudo`/`env`-style wrappers
+    pub program: Option<String>,
+    /// Flags that must all be present; `-r|--recursive` lists alternatives and `-r` also
+    /// matches combined short flags such as `-rf`
+    pub flags: Vec<String>,
+    /// Regex one non-flag argument must match in full
+    pub arg: Option<String>,
+    /// Programs a later stage of the same pipeline must run (`curl ... | sh`)
+    pub pipe_to: Vec<String>,
+    /// Built-in semantic check (`rm`, `dd`, `permissions`, `git-push`, `kubectl-delete`, `sql`)
+    /// that decides the severity and reason itself unless `severity` is given
+    pub check: Option<String>,
+    /// Turn off a built-in rule with the same id
+    pub disabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RuleDef>,
+}
+
+/// A compiled rule
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub id: String,
+    pub severity: Severity,
+    pub category: String,
+    pub explanation: String,
+    pub remediation: String,
+    pattern: Option<Regex>,
+    program: Option<Regex>,
+    flags: Vec<Vec<String>>,
+    arg: Option<Regex>,
+    pipe_to: Vec<String>,
+    check: Option<Check>,
+    /// Severity set by the rule rather than by its check
+    fixed_severity: bool,
+}
+
+/// Wrappers that run their arguments as a command
+const WRAPPERS: [&str; 9] = ["sudo", "doas", "env", "nice", "nohup", "time", "command", "exec", "stdbuf"];
+
+/// Options of `sudo`/`doas` that take a value
+const WRAPPER_VALUE_FLAGS: [&str; 6] = ["-u", "-g", "-h", "-p", "-C", "-D"];
+
+/// Program and arguments of a stage with wrappers and environment assignments removed
+pub fn unwrap_stage(stage: &[String]) -> &[String] {
+    let mut i = 0;
+    while i < stage.len() {
+        let token = stage[i].as_str();
+        if is_assignment(token) {
+            i += 1;
+        } else if WRAPPERS.contains(&token) {
+            i += 1;
+            while i < stage.len() && (stage[i].starts_with('-') || is_assignment(&stage[i])) {
+                i += if WRAPPER_VALUE_FLAGS.contains(&stage[i].as_str()) { 2 } else { 1 };
+            }
+        } else {
+            break;
+        }
+    }
+    &stage[i.min(stage.len())..]
+}
+
+/// Whether `tokens` contain the flag `spec` (`--force`, or `-f` alone or inside `-rf`)
+fn has_flag(tokens: &[String], spec: &str) -> bool {
+    tokens.iter().take_while(|t| *t != "--").any(|t| {
+        if spec.starts_with("--") {
+            t == spec || t.strip_prefix(spec).is_some_and(|rest| rest.starts_with('='))
+        } else {
+            let Some(letter) = spec.strip_prefix('-') else { return false };
+            t == spec || (letter.len() == 1 && t.starts_with('-') && !t.starts_with("--")
+                && t[1..].chars().all(|c| c.is_ascii_alphabetic()) && t[1..].contains(letter))
+        }
+    })
+}
+
+fn compile(pattern: &str, anchored: bool, id: &str) -> Result<Regex> {
+    let source = if anchored { format!("^(?:{})$", pattern) } else { pattern.to_string() };
+    Regex::new(&source).with_context(|| format!("Invalid regex in rule '{}'", id))
+}
+
+impl Rule {
+    fn compile(def: &RuleDef) -> Result<Rule> {
+        if def.id.is_empty() {
+            bail!("Rule without an id");
+        }
+        if def.pattern.is_none() && def.program.is_none() && def.check.is_none() {
+            bail!("Rule '{}' needs a pattern, a program or a check", def.id);
+        }
+        let check = match def.check.as_deref() {
+            Some(name) => Some(Check::from_name(name).with_context(|| {
+                format!("Unknown check '{}' in rule '{}' (known: {})", name, def.id, Check::NAMES.join(", "))
+            })?),
+            None => None,
+        };
+        Ok(Rule {
+            id: def.id.clone(),
+            severity: def.severity.unwrap_or(Severity::Medium),
+            category: if def.category.is_empty() { "custom".to_string() } else { def.category.clone() },
+            explanation: def.explanation.clone(),
+            remediation: def.remediation.clone(),
+            pattern: def.pattern.as_deref().map(|p| compile(p, false, &def.id)).transpose()?,
+            program: def.program.as_deref().map(|p| compile(p, true, &def.id)).transpose()?,
+            flags: def.flags.iter().map(|f| f.split('|').map(|a| a.trim().to_string()).collect()).collect(),
+            arg: def.arg.as_deref().map(|p| compile(p, true, &def.id)).transpose()?,
+            pipe_to: def.pipe_to.clone(),
+            check,
+            fixed_severity: def.severity.is_some(),
+        })
+    }
+
+    /// Severity and reason if the command line triggers this rule
+    pub fn evaluate(&self, command: &str) -> Option<(Severity, String)> {
+        if let Some(ref pattern) = self.pattern {
+            if !pattern.is_match(command) {
+                return None;
+            }
+        }
+        if self.program.is_none() && self.check.is_none() {
+            return Some((self.severity, self.explanation.clone()));
+        }
+        let chains = pipelines(command);
+        let stages = chains.iter().flat_map(|chain| (0..chain.len()).map(move |i| (chain, i)));
+        // The most severe stage decides (`rm -rf ./x && rm -rf /`)
+        let finding = stages.filter(|(chain, i)| self.stage_matches(chain, *i))
+            .filter_map(|(chain, i)| match self.check {
+                Some(check) => check.run(chain, i),
+                None => Some((self.severity, self.explanation.clone())),
+            })
+            .max_by_key(|(severity, _)| *severity);
+        finding.map(|(severity, reason)| (if self.fixed_severity { self.severity } else { severity }, reason))
+    }
+
+    /// Whether stage `i` satisfies the token part of the rule (always true without `program`)
+    fn stage_matches(&self, chain: &[Vec<String>], i: usize) -> bool {
+        let Some(ref program) = self.program else { return true };
+        let stage = unwrap_stage(&chain[i]);
+        let Some((name, args)) = stage.split_first() else { return false };
+        let name = name.rsplit('/').next().unwrap_or(name);
+        program.is_match(name)
+            && self.flags.iter().all(|alts| alts.iter().any(|f| has_flag(args, f)))
+            && self.arg.as_ref().is_none_or(|re| {
+                let mut after_dashes = false;
+                args.iter().any(|a| {
+                    if a == "--" && !after_dashes {
+                        after_dashes = true;
+                        return false;
+                    }
+                    (after_dashes || !a.starts_with('-') || a.len() == 1) && re.is_match(a)
+                })
+            })
+            && (self.pipe_to.is_empty() || chain[i + 1..].iter().any(|later| {
+                unwrap_stage(later).first()
+                    .map(|p| p.rsplit('/').next().unwrap_or(p))
+                    .is_some_and(|p| self.pipe_to.iter().any(|t| t == p))
+            }))
+    }
+}
+
+fn builtin_defs() -> Vec<RuleDef> {
+    let rule = |id: &str, severity: Severity, category: &str, explanation: &str, remediation: &str| RuleDef {
+        id: id.to_string(),
+        severity: Some(severity),
+        category: category.to_string(),
+        explanation: explanation.to_string(),
+        remediation: remediation.to_string(),
+        ..Default::default()
+    };
+    // Semantic checks pick their own severity
+    let semantic = |id: &str, check: &str, category: &str, explanation: &str, remediation: &str| RuleDef {
+        check: Some(check.to_string()),
+        severity: None,
+        ..rule(id, Severity::Medium, category, explanation, remediation)
+    };
+    const DISKS: &str = r"/dev/(sd[a-z]|nvme\d|hd[a-z]|vd[a-z]|xvd[a-z]|mmcblk\d|disk\d)\S*";
+    const SHELLS: [&str; 10] = ["sh", "bash", "zsh", "dash", "ksh", "fish", "python", "python3", "perl", "ruby"];
+    vec![
+        semantic("rm-dangerous-target", "rm", "data-loss", "Recursively deletes the root, home, a system directory or a path built from a possibly empty variable", "Double-check the path; use `${VAR:?}` so an unset variable aborts, or `trash` instead"),
+        RuleDef { program: Some("rm".into()), flags: vec!["--no-preserve-root".into()],
+            ..rule("rm-no-preserve-root", Severity::Critical, "data-loss", "Disables the safeguard against deleting /", "Never needed in normal use; remove the flag") },
+        semantic("dd-to-disk", "dd", "disk", "Overwrites a block device", "Confirm the device with `lsblk` before writing"),
+        RuleDef { pattern: Some(format!(r">\s*{}", DISKS)),
+            ..rule("redirect-to-disk", Severity::Critical, "disk", "Redirects output onto a block device", "Write to a file instead of the raw device") },
+        RuleDef { program: Some(r"mkfs(\.\w+)?|mke2fs|wipefs".into()),
+            ..rule("mkfs", Severity::High, "disk", "Creates a filesystem, erasing the device", "Confirm the device with `lsblk` before formatting") },
+        RuleDef { pattern: Some(r":\(\)\s*\{\s*:\s*\|\s*:\s*&\s*\}\s*;\s*:".into()),
+            ..rule("fork-bomb", Severity::Critical, "denial-of-service", "Fork bomb: spawns processes until the system hangs", "Do not run; set `ulimit -u` to limit processes") },
+        RuleDef { program: Some("curl|wget".into()), pipe_to: SHELLS.iter().map(|s| s.to_string()).collect(),
+            ..rule("download-pipe-shell", Severity::High, "remote-code", "Runs a downloaded script without reviewing it", "Download to a file, read it, then run it") },
+        semantic("dangerous-permissions", "permissions", "permissions", "Makes files world-writable or recursively changes ownership or permissions of a system directory", "Use 755 for directories and 644 for files, grant access to a group, and limit the change to the files that need it"),
+        RuleDef { program: Some("mv".into()), arg: Some(r"/\*?".into()),
+            ..rule("mv-root", Severity::Critical, "data-loss", "Moves the root directory", "Check the source path") },
+        RuleDef { program: Some("shutdown|reboot|poweroff|halt".into()),
+            ..rule("power-off", Severity::Medium, "availability", "Stops or restarts the machine", "Check which host you are on (`hostname`) before running it") },
+        RuleDef { program: Some("init|telinit".into()), arg: Some("[06]".into()),
+            ..rule("init-runlevel", Severity::Medium, "availability", "Switches to the halt or reboot runlevel", "Use `systemctl poweroff` or `reboot` on the intended host") },
+        semantic("git-force-push", "git-push", "data-loss", "Overwrites or deletes the history of a shared branch", "Push to a feature branch, or use `git push --force-with-lease`"),
+        RuleDef { program: Some("git".into()), arg: Some("reset".into()), flags: vec!["--hard".into()],
+            ..rule("git-reset-hard", Severity::Low, "data-loss", "Discards uncommitted changes", "Run `git stash` first") },
+        semantic("kubectl-delete", "kubectl-delete", "kubernetes", "Deletes namespaces, production resources or everything matching", "Check `kubectl config current-context` and run with `--dry-run=client` first"),
+        semantic("destructive-sql", "sql", "database", "Runs DROP, TRUNCATE, or DELETE/UPDATE without WHERE", "Add a WHERE clause, run inside a transaction, or take a backup first"),
+    ]
+}
+
+/// Built-in rules merged with user rules: same id replaces, `disabled = true` removes
+pub fn merge_rules(builtin: Vec<RuleDef>, user: Vec<RuleDef>) -> Result<Vec<Rule>> {
+    let mut defs = builtin;
+    for def in user {
+        match defs.iter().position(|d| d.id == def.id) {
+            Some(i) if def.disabled => { defs.remove(i); },
+            Some(i) => defs[i] = def,
+            None if def.disabled => {},
+            None => defs.push(def),
+        }
+    }
+    defs.iter().map(Rule::compile).collect()
+}
+
+/// The built-in rules alone
+pub fn builtin_rules() -> Vec<Rule> {
+    merge_rules(builtin_defs(), vec![]).expect("built-in rules compile")
+}
+
+/// Default rules file: `~/.config/heist/rules.toml`
+pub fn default_rules_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("heist").join("rules.toml"))
+}
+
+/// Read user rules from a TOML file of `[[rule]]` tables
+pub fn load_rules_file(path: &Path) -> Result<Vec<RuleDef>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read rules file {:?}", path))?;
+    let file: RulesFile = serde_toml::from_str(&text).with_context(|| format!("Failed to parse rules file {:?}", path))?;
+    Ok(file.rule)
+}
+
+/// Rules for this run: built-ins plus `--rules FILE` or the default rules file if present.
+/// Errors are reported once and fall back to the built-in rules.
+pub fn rules(args: &CliArgs) -> &'static [Rule] {
+    static RULES: OnceLock<Vec<Rule>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        let path = args.rules.as_ref().map(PathBuf::from)
+            .or_else(|| default_rules_path().filter(|p| p.exists()));
+        let user = path.map(|p| load_rules_file(&p)).transpose();
+        user.and_then(|user| merge_rules(builtin_defs(), user.unwrap_or_default()))
+            .unwrap_or_else(|e| {
+                log_error!("Failed to load dangerous-command rules: {:#}", e);
+                builtin_rules()
+            })
+    })
+}
+
+/// A rule triggered by a command
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleMatch {
+    pub id: String,
+    pub severity: Severity,
+    pub category: String,
+    pub explanation: String,
+    /// Why this particular command triggered the rule
+    pub reason: String,
+    pub remediation: String,
+}
+
+/// A distinct dangerous command line with every rule it triggers, most severe first
+#[derive(Debug, Clone, Serialize)]
+pub struct DangerousCommand {
+    pub command: String,
+    pub severity: Severity,
+    pub count: usize,
+    pub last_run: Option<DateTime<Local>>,
+    pub matches: Vec<RuleMatch>,
+}
+
+/// Rules triggered by one command line, most severe first
+pub fn check(command: &str, rules: &[Rule]) -> Vec<RuleMatch> {
+    let mut matches: Vec<RuleMatch> = rules.iter()
+        .filter_map(|r| r.evaluate(command).map(|(severity, reason)| RuleMatch {
+            id: r.id.clone(),
+            severity,
+            category: r.category.clone(),
+            explanation: r.explanation.clone(),
+            reason,
+            remediation: r.remediation.clone(),
+        }))
+        .collect();
+    matches.sort_by_key(|m| std::cmp::Reverse(m.severity));
+    matches
+}
+
+/// Dangerous commands in history, most severe first, then most recent
+pub fn scan(history: &[HistoryEntry], rules: &[Rule]) -> Vec<DangerousCommand> {
+    let mut found: Vec<DangerousCommand> = vec![];
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for entry in history {
+        let command = entry.command.trim();
+        if let Some(&i) = index.get(command) {
+            found[i].count += 1;
+            found[i].last_run = entry.timestamp.or(found[i].last_run);
+            continue;
+        }
+        let matches = check(command, rules);
+        let Some(first) = matches.first() else { continue };
+        index.insert(command, found.len());
+        found.push(DangerousCommand { command: command.to_string(), severity: first.severity, count: 1, last_run: entry.timestamp, matches });
+    }
+    found.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| b.last_run.cmp(&a.last_run)));
+    found
+}
+
+/// Print findings with their explanations and remediation hints
+pub fn print_dangerous(found: &[DangerousCommand]) {
+    println!("\nDangerous Command Flagging:");
+    if found.is_empty() {
+        println!("No dangerous commands found in history.");
+        return;
+    }
+    let count = |s: Severity| found.iter().filter(|d| d.severity == s).count();
+    println!("{} critical, {} high, {} medium, {} low", count(Severity::Critical), count(Severity::High), count(Severity::Medium), count(Severity::Low));
+    for d in found {
+        let times = if d.count > 1 { format!(" (×{})", d.count) } else { String::new() };
+        println!("\n⚠️  [{}] {}{}", d.severity.label(), d.command, times);
+        for m in &d.matches {
+            println!("    ↳ {} ({}): {}", m.id, m.category, m.reason);
+            if !m.remediation.is_empty() {
+                println!("      Fix: {}", m.remediation);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(command: &str, rules: &[Rule]) -> Vec<String> {
+        check(command, rules).into_iter().map(|m| m.id).collect()
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let rules = builtin_rules();
+        assert_eq!(ids("sudo rm -rf /", &rules), vec!["rm-dangerous-target"]);
+        assert!(ids("rm -rf ./build", &rules).is_empty());
+        assert_eq!(ids("curl -fsSL https://x.sh/install | sudo bash", &rules), vec!["download-pipe-shell"]);
+        assert!(ids("curl https://x | jq .", &rules).is_empty());
+        assert_eq!(ids("dd if=img.iso of=/dev/sdb bs=4M", &rules), vec!["dd-to-disk"]);
+        assert_eq!(ids("git push --force origin main", &rules), vec!["git-force-push"]);
+        assert!(ids("echo rm -rf /", &rules).is_empty());
+    }
+
+    #[test]
+    fn test_user_rules_file() {
+        let text = r#"
+            [[rule]]
+            id = "kubectl-delete"
+            disabled = true
+
+            [[rule]]
+            id = "destructive-sql"
+            check = "sql"
+            severity = "critical"
+
+            [[rule]]
+            id = "kubectl-delete-namespace"
+            severity = "high"
+            category = "kubernetes"
+            program = "kubectl"
+            arg = "ns|namespace"
+            flags = []
+            explanation = "Deletes every resource in the namespace"
+        "#;
+        let file: RulesFile = serde_toml::from_str(text).unwrap();
+        let rules = merge_rules(builtin_defs(), file.rule).unwrap();
+        assert_eq!(ids("kubectl delete ns staging", &rules), vec!["kubectl-delete-namespace"]);
+        let sql = check("psql -c 'truncate logs'", &rules);
+        assert_eq!((sql[0].severity, sql[0].reason.as_str()), (Severity::Critical, "`truncate logs` permanently removes a table's data via psql"));
+        let bad = RuleDef { id: "x".into(), program: Some("(".into()), ..Default::default() };
+        assert!(merge_rules(vec![], vec![bad]).is_err());
+        let unknown = RuleDef { id: "y".into(), check: Some("nope".into()), ..Default::default() };
+        assert!(merge_rules(vec![], vec![unknown]).is_err());
+    }
+}
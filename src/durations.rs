@@ -0,0 +1,241 @@
+//! Command duration analytics: slowest commands, time per tool, weekly trends and outliers
+//! Only entries whose source records a run time take part
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+use serde::Serialize;
+
+use crate::models::HistoryEntry;
+use crate::tokenizer::command_path;
+
+/// A command must have run at least this often before it can have outliers
+const MIN_OUTLIER_SAMPLES: usize = 5;
+
+/// A run counts as an outlier when it takes this many times the command's median
+const OUTLIER_FACTOR: f64 = 3.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimedCommand {
+    pub command: String,
+    pub duration_ms: u64,
+    pub timestamp: Option<DateTime<Local>>,
+}
+
+/// Time spent in one tool (program and subcommand)
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolTime {
+    pub tool: String,
+    pub runs: usize,
+    pub total_ms: u64,
+    pub mean_ms: u64,
+    /// Total spread over the weeks covered by timed history
+    pub hours_per_week: f64,
+}
+
+/// Total run time in one week (weeks start on Monday)
+#[derive(Debug, Clone, Serialize)]
+pub struct WeekTime {
+    pub week: NaiveDate,
+    pub runs: usize,
+    pub total_ms: u64,
+    pub mean_ms: u64,
+}
+
+/// A run much slower than the same command usually takes
+#[derive(Debug, Clone, Serialize)]
+pub struct DurationOutlier {
+    pub command: String,
+    pub duration_ms: u64,
+    pub median_ms: u64,
+    pub timestamp: Option<DateTime<Local>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DurationReport {
+    /// Entries with a recorded duration
+    pub timed: usize,
+    pub total_ms: u64,
+    pub slowest: Vec<TimedCommand>,
+    pub tools: Vec<ToolTime>,
+    pub weekly: Vec<WeekTime>,
+    pub outliers: Vec<DurationOutlier>,
+}
+
+/// Human-readable duration: `250ms`, `12.3s`, `4m 05s`, `2h 03m`
+pub fn format_duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    match secs {
+        0 => format!("{}ms", ms),
+        1..=59 => format!("{:.1}s", ms as f64 / 1000.0),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+fn median(values: &mut [u64]) -> u64 {
+    values.sort_unstable();
+    match values.len() {
+        0 => 0,
+        n if n % 2 == 1 => values[n / 2],
+        n => (values[n / 2 - 1] + values[n / 2]) / 2,
+    }
+}
+
+/// Build the report from entries with a recorded duration
+pub fn duration_report(history: &[HistoryEntry]) -> DurationReport {
+    let timed: Vec<(&HistoryEntry, u64)> = history.iter()
+        .filter_map(|e| e.duration_ms.map(|d| (e, d)))
+        .collect();
+    let total_ms = timed.iter().map(|(_, d)| d).sum();
+
+    let mut slowest: Vec<TimedCommand> = timed.iter()
+        .map(|(e, d)| TimedCommand { command: e.command.clone(), duration_ms: *d, timestamp: e.timestamp })
+        .collect();
+    slowest.sort_by(|a, b| b.duration_ms.cmp(&a.duration_ms).then_with(|| a.timestamp.cmp(&b.timestamp)));
+    slowest.truncate(10);
+
+    let first = timed.iter().filter_map(|(e, _)| e.timestamp).min();
+    let last = timed.iter().filter_map(|(e, _)| e.timestamp).max();
+    let weeks = match (first, last) {
+        (Some(first), Some(last)) => (last.signed_duration_since(first).num_seconds() as f64 / (7.0 * 86400.0)).max(1.0),
+        _ => 1.0,
+    };
+    let mut by_tool: HashMap<String, (usize, u64)> = HashMap::new();
+    for (entry, d) in &timed {
+        let tool = command_path(&entry.command, 2).join(" ");
+        if tool.is_empty() {
+            continue;
+        }
+        let stats = by_tool.entry(tool).or_default();
+        stats.0 += 1;
+        stats.1 += d;
+    }
+    let mut tools: Vec<ToolTime> = by_tool.into_iter()
+        .map(|(tool, (runs, total))| ToolTime {
+            tool,
+            runs,
+            total_ms: total,
+            mean_ms: total / runs as u64,
+            hours_per_week: total as f64 / 3_600_000.0 / weeks,
+        })
+        .collect();
+    tools.sort_by(|a, b| b.total_ms.cmp(&a.total_ms).then_with(|| a.tool.cmp(&b.tool)));
+    tools.truncate(20);
+
+    let mut by_week: BTreeMap<NaiveDate, (usize, u64)> = BTreeMap::new();
+    for (entry, d) in &timed {
+        let Some(ts) = entry.timestamp else { continue };
+        let day = ts.date_naive();
+        let week = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+        let stats = by_week.entry(week).or_default();
+        stats.0 += 1;
+        stats.1 += d;
+    }
+    let weekly: Vec<WeekTime> = by_week.into_iter()
+        .rev()
+        .take(12)
+        .rev()
+        .map(|(week, (runs, total))| WeekTime { week, runs, total_ms: total, mean_ms: total / runs as u64 })
+        .collect();
+
+    let mut by_command: HashMap<&str, Vec<u64>> = HashMap::new();
+    for (entry, d) in &timed {
+        by_command.entry(entry.command.trim()).or_default().push(*d);
+    }
+    let medians: HashMap<&str, u64> = by_command.into_iter()
+        .filter(|(_, runs)| runs.len() >= MIN_OUTLIER_SAMPLES)
+        .map(|(cmd, mut runs)| (cmd, median(&mut runs)))
+        .collect();
+    let mut outliers: Vec<DurationOutlier> = timed.iter()
+        .filter_map(|(entry, d)| {
+            let median_ms = *medians.get(entry.command.trim())?;
+            // Ignore sub-second jitter on fast commands
+            (*d as f64 > median_ms as f64 * OUTLIER_FACTOR && d - median_ms >= 1000).then(|| DurationOutlier {
+                command: entry.command.clone(),
+                duration_ms: *d,
+                median_ms,
+                timestamp: entry.timestamp,
+            })
+        })
+        .collect();
+    outliers.sort_by(|a, b| {
+        let ratio = |o: &DurationOutlier| o.duration_ms as f64 / o.median_ms.max(1) as f64;
+        ratio(b).total_cmp(&ratio(a)).then_with(|| a.timestamp.cmp(&b.timestamp))
+    });
+    outliers.truncate(10);
+
+    DurationReport { timed: timed.len(), total_ms, slowest, tools, weekly, outliers }
+}
+
+/// Print slowest commands, time per tool, weekly totals and outliers
+pub fn print_duration_report(report: &DurationReport) {
+    println!("\nCommand durations ({} commands with a recorded run time, {} total):", report.timed, format_duration(report.total_ms));
+    if report.timed == 0 {
+        println!("No durations recorded. Use zsh EXTENDED_HISTORY or live tracking (contrib/heist_live_tracking.sh).");
+        return;
+    }
+    println!("\nTime per tool:");
+    println!("{:<24} {:>6} {:>10} {:>10} {:>8}", "Tool", "Runs", "Total", "Mean", "h/week");
+    for t in &report.tools {
+        println!("{:<24} {:>6} {:>10} {:>10} {:>8.2}", t.tool, t.runs, format_duration(t.total_ms), format_duration(t.mean_ms), t.hours_per_week);
+    }
+    println!("\nSlowest commands:");
+    for c in &report.slowest {
+        let when = c.timestamp.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "-".to_string());
+        println!("  {:>10}  {}  {}", format_duration(c.duration_ms), when, c.command);
+    }
+    println!("\nWeekly trend:");
+    for w in &report.weekly {
+        println!("  {}  {:>6} runs  {:>10} total  {:>10} mean", w.week, w.runs, format_duration(w.total_ms), format_duration(w.mean_ms));
+    }
+    println!("\nOutliers (over {}× the command's median):", OUTLIER_FACTOR);
+    if report.outliers.is_empty() {
+        println!("  (none)");
+    }
+    for o in &report.outliers {
+        let when = o.timestamp.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "-".to_string());
+        println!("  {:>10} vs {:>8} usual  {}  {}", format_duration(o.duration_ms), format_duration(o.median_ms), when, o.command);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn timed(day: u32, command: &str, duration_ms: Option<u64>) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: Local.with_ymd_and_hms(2025, 3, day, 12, 0, 0).single(),
+            command: command.to_string(),
+            duration_ms,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(250), "250ms");
+        assert_eq!(format_duration(12_300), "12.3s");
+        assert_eq!(format_duration(245_000), "4m 05s");
+        assert_eq!(format_duration(7_380_000), "2h 03m");
+    }
+
+    #[test]
+    fn test_duration_report() {
+        let mut history: Vec<HistoryEntry> = (3..=7).map(|d| timed(d, "cargo build", Some(20_000))).collect();
+        history.push(timed(10, "cargo build", Some(90_000)));
+        history.push(timed(10, "ls", Some(5)));
+        history.push(timed(10, "vim", None));
+        let report = duration_report(&history);
+        assert_eq!(report.timed, 7);
+        assert_eq!(report.tools[0].tool, "cargo build");
+        assert_eq!(report.tools[0].total_ms, 190_000);
+        assert_eq!(report.slowest[0].duration_ms, 90_000);
+        // Monday 3 March and Monday 10 March
+        assert_eq!(report.weekly.len(), 2);
+        assert_eq!(report.weekly[0].week, NaiveDate::from_ymd_opt(2025, 3, 3).unwrap());
+        assert_eq!(report.outliers.len(), 1);
+        assert_eq!(report.outliers[0].median_ms, 20_000);
+    }
+}
@@ -0,0 +1,205 @@
+//! Exit-status analytics: failure rates, common exit codes, failure streaks and fixes
+//! Only entries whose source records an exit status take part
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::models::HistoryEntry;
+use crate::tokenizer::command_path;
+
+/// Commands looked ahead of a failure for a successful re-run
+const FIX_WINDOW: usize = 5;
+
+/// Failure statistics for one tool (program and subcommand)
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolFailures {
+    pub tool: String,
+    pub runs: usize,
+    pub failures: usize,
+    /// Most common non-zero exit codes with their counts
+    pub top_codes: Vec<(i32, usize)>,
+}
+
+impl ToolFailures {
+    pub fn failure_rate(&self) -> f64 {
+        if self.runs == 0 { 0.0 } else { self.failures as f64 / self.runs as f64 }
+    }
+}
+
+/// Consecutive failing commands
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureStreak {
+    pub length: usize,
+    pub start: Option<DateTime<Local>>,
+    pub commands: Vec<String>,
+}
+
+/// A tool that failed, was followed by other commands and then succeeded
+#[derive(Debug, Clone, Serialize)]
+pub struct FixPattern {
+    pub tool: String,
+    pub fixes: usize,
+    /// Most common first command run between the failure and the successful re-run
+    pub common_fix: String,
+    pub common_fix_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitStatusReport {
+    /// Entries with a known exit status
+    pub recorded: usize,
+    pub failures: usize,
+    pub tools: Vec<ToolFailures>,
+    pub streaks: Vec<FailureStreak>,
+    pub fixes: Vec<FixPattern>,
+}
+
+fn tool_of(command: &str) -> String {
+    command_path(command, 2).join(" ")
+}
+
+/// Build the report from entries with a recorded exit status
+pub fn exit_status_report(history: &[HistoryEntry]) -> ExitStatusReport {
+    let known: Vec<&HistoryEntry> = history.iter().filter(|e| e.exit_code.is_some()).collect();
+    let failures = known.iter().filter(|e| e.failed() == Some(true)).count();
+
+    let mut by_tool: HashMap<String, (usize, usize, HashMap<i32, usize>)> = HashMap::new();
+    for entry in &known {
+        let tool = tool_of(&entry.command);
+        if tool.is_empty() {
+            continue;
+        }
+        let stats = by_tool.entry(tool).or_default();
+        stats.0 += 1;
+        if let Some(code) = entry.exit_code.filter(|c| *c != 0) {
+            stats.1 += 1;
+            *stats.2.entry(code).or_insert(0) += 1;
+        }
+    }
+    let mut tools: Vec<ToolFailures> = by_tool.into_iter()
+        .filter(|(_, (_, failed, _))| *failed > 0)
+        .map(|(tool, (runs, failures, codes))| {
+            let mut top_codes: Vec<(i32, usize)> = codes.into_iter().collect();
+            top_codes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            top_codes.truncate(3);
+            ToolFailures { tool, runs, failures, top_codes }
+        })
+        .collect();
+    tools.sort_by(|a, b| b.failures.cmp(&a.failures)
+        .then_with(|| b.failure_rate().total_cmp(&a.failure_rate()))
+        .then_with(|| a.tool.cmp(&b.tool)));
+    tools.truncate(20);
+
+    // Streaks are broken by any success; entries without a status are skipped
+    let mut streaks: Vec<FailureStreak> = vec![];
+    let mut current: Vec<&HistoryEntry> = vec![];
+    for entry in known.iter().copied().chain(std::iter::once(&HistoryEntry { exit_code: Some(0), ..Default::default() })) {
+        if entry.failed() == Some(true) {
+            current.push(entry);
+        } else if !current.is_empty() {
+            if current.len() >= 2 {
+                streaks.push(FailureStreak {
+                    length: current.len(),
+                    start: current[0].timestamp,
+                    commands: current.iter().take(5).map(|e| e.command.clone()).collect(),
+                });
+            }
+            current.clear();
+        }
+    }
+    streaks.sort_by(|a, b| b.length.cmp(&a.length).then_with(|| a.start.cmp(&b.start)));
+    streaks.truncate(5);
+
+    // Failure → other commands → success of the same tool
+    let mut fixes: HashMap<String, HashMap<String, usize>> = HashMap::new();
+    for (i, entry) in known.iter().enumerate() {
+        if entry.failed() != Some(true) {
+            continue;
+        }
+        let tool = tool_of(&entry.command);
+        let window = &known[i + 1..(i + 1 + FIX_WINDOW).min(known.len())];
+        let Some(pos) = window.iter().position(|e| tool_of(&e.command) == tool) else { continue };
+        if pos == 0 || window[pos].failed() != Some(false) {
+            continue;
+        }
+        let fix = tool_of(&window[0].command);
+        *fixes.entry(tool).or_default().entry(fix).or_insert(0) += 1;
+    }
+    let mut fixes: Vec<FixPattern> = fixes.into_iter()
+        .map(|(tool, counts)| {
+            let total = counts.values().sum();
+            let (common_fix, common_fix_count) = counts.into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                .unwrap_or_default();
+            FixPattern { tool, fixes: total, common_fix, common_fix_count }
+        })
+        .collect();
+    fixes.sort_by(|a, b| b.fixes.cmp(&a.fixes).then_with(|| a.tool.cmp(&b.tool)));
+    fixes.truncate(10);
+
+    ExitStatusReport { recorded: known.len(), failures, tools, streaks, fixes }
+}
+
+/// Print failure rates, exit codes, streaks and fixes
+pub fn print_exit_status_report(report: &ExitStatusReport) {
+    println!("\nExit-status analytics ({} commands with a recorded status):", report.recorded);
+    if report.recorded == 0 {
+        println!("No exit codes recorded. Enable live tracking (contrib/heist_live_tracking.sh) to capture them.");
+        return;
+    }
+    println!("Failed: {} ({:.1}%)", report.failures, report.failures as f64 / report.recorded as f64 * 100.0);
+    println!("\n{:<24} {:>6} {:>8} {:>6}  Exit codes", "Tool", "Runs", "Failed", "Rate");
+    for t in &report.tools {
+        let codes: Vec<String> = t.top_codes.iter().map(|(code, n)| format!("{}×{}", code, n)).collect();
+        println!("{:<24} {:>6} {:>8} {:>5.1}%  {}", t.tool, t.runs, t.failures, t.failure_rate() * 100.0, codes.join(", "));
+    }
+    println!("\nLongest failure streaks:");
+    if report.streaks.is_empty() {
+        println!("  (none)");
+    }
+    for s in &report.streaks {
+        let start = s.start.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "-".to_string());
+        println!("  {:>3} failures from {}: {}", s.length, start, s.commands.join(" → "));
+    }
+    println!("\nFailures most often fixed before a successful re-run:");
+    if report.fixes.is_empty() {
+        println!("  (none)");
+    }
+    for f in &report.fixes {
+        println!("  {:<24} {:>4}x  usually after '{}' ({}x)", f.tool, f.fixes, f.common_fix, f.common_fix_count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(command: &str, exit_code: Option<i32>) -> HistoryEntry {
+        HistoryEntry { command: command.to_string(), exit_code, ..Default::default() }
+    }
+
+    #[test]
+    fn test_exit_status_report() {
+        let history = vec![
+            run("cargo build", Some(101)),
+            run("vim src/main.rs", Some(0)),
+            run("cargo build", Some(0)),
+            run("ls", None),
+            run("cargo build", Some(101)),
+            run("gti status", Some(127)),
+            run("git stauts", Some(1)),
+            run("git status", Some(0)),
+        ];
+        let report = exit_status_report(&history);
+        assert_eq!((report.recorded, report.failures), (7, 4));
+        assert_eq!(report.tools[0].tool, "cargo build");
+        assert_eq!((report.tools[0].runs, report.tools[0].failures), (3, 2));
+        assert_eq!(report.tools[0].top_codes, vec![(101, 2)]);
+        assert_eq!(report.streaks.len(), 1);
+        assert_eq!(report.streaks[0].length, 3);
+        assert_eq!(report.fixes.len(), 1);
+        assert_eq!((report.fixes[0].tool.as_str(), report.fixes[0].common_fix.as_str()), ("cargo build", "vim"));
+    }
+}
@@ -0,0 +1,121 @@
+//! Per-host analytics from recorded hostnames (live tracker, `~/.heist_hosts/<host>/`)
+//! Entries without a recorded host are counted for the local machine
+
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+use crate::models::HistoryEntry;
+use crate::tokenizer::command_path;
+
+/// Name of this machine as the live tracker records it: `uname -n` (the kernel's hostname,
+/// which bash's `$HOSTNAME` and zsh's `$HOST` also report), then `%COMPUTERNAME%`, then `localhost`
+pub fn local_hostname() -> String {
+    static HOSTNAME: OnceLock<String> = OnceLock::new();
+    HOSTNAME.get_or_init(|| {
+        Command::new("uname").arg("-n").output().ok()
+            .filter(|out| out.status.success())
+            .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
+            .or_else(|| std::env::var("COMPUTERNAME").ok())
+            .map(|h| h.trim().to_string())
+            .filter(|h| !h.is_empty())
+            .unwrap_or_else(|| "localhost".to_string())
+    }).clone()
+}
+
+/// Activity on one machine
+#[derive(Debug, Clone, Serialize)]
+pub struct HostStats {
+    pub host: String,
+    pub commands: usize,
+    /// Most used tools (program and subcommand)
+    pub top_commands: Vec<(String, usize)>,
+    /// Most used tools never run on any other host
+    pub unique_commands: Vec<(String, usize)>,
+}
+
+/// Group history by host, busiest first
+pub fn host_stats(history: &[HistoryEntry], local: &str) -> Vec<HostStats> {
+    let mut by_host: HashMap<&str, (usize, HashMap<String, usize>)> = HashMap::new();
+    for entry in history {
+        let host = entry.hostname.as_deref().unwrap_or(local);
+        let stats = by_host.entry(host).or_default();
+        stats.0 += 1;
+        let tool = command_path(&entry.command, 2).join(" ");
+        if !tool.is_empty() {
+            *stats.1.entry(tool).or_insert(0) += 1;
+        }
+    }
+    let mut hosts_per_tool: HashMap<&str, usize> = HashMap::new();
+    for (_, tools) in by_host.values() {
+        for tool in tools.keys() {
+            *hosts_per_tool.entry(tool).or_insert(0) += 1;
+        }
+    }
+    let shared: HashSet<&str> = hosts_per_tool.into_iter().filter(|(_, n)| *n > 1).map(|(tool, _)| tool).collect();
+    let top = |tools: Vec<(String, usize)>| {
+        let mut tools = tools;
+        tools.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        tools.truncate(5);
+        tools
+    };
+    let mut stats: Vec<HostStats> = by_host.iter()
+        .map(|(host, (commands, tools))| {
+            // Only meaningful when there is another host to compare with
+            let unique = if by_host.len() > 1 {
+                tools.iter().filter(|(t, _)| !shared.contains(t.as_str())).map(|(t, n)| (t.clone(), *n)).collect()
+            } else {
+                vec![]
+            };
+            HostStats {
+                host: host.to_string(),
+                commands: *commands,
+                top_commands: top(tools.iter().map(|(t, n)| (t.clone(), *n)).collect()),
+                unique_commands: top(unique),
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| b.commands.cmp(&a.commands).then_with(|| a.host.cmp(&b.host)));
+    stats
+}
+
+/// Print commands, top tools and host-only tools per host
+pub fn print_host_stats(stats: &[HostStats]) {
+    println!("\nPer-host command stats:");
+    for h in stats {
+        let join = |tools: &[(String, usize)]| tools.iter().map(|(t, n)| format!("{} ({})", t, n)).collect::<Vec<_>>().join(", ");
+        println!("\n{} ({} commands)", h.host, h.commands);
+        println!("  Top:    {}", join(&h.top_commands));
+        if !h.unique_commands.is_empty() {
+            println!("  Only here: {}", join(&h.unique_commands));
+        }
+    }
+    if stats.len() < 2 {
+        println!("\nOnly one host seen. Live tracking records the host; copy other machines' history files to ~/.heist_hosts/<host>/.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_stats() {
+        let entry = |command: &str, host: Option<&str>| HistoryEntry { command: command.to_string(), hostname: host.map(String::from), ..Default::default() };
+        let history = vec![
+            entry("git status", None),
+            entry("cargo build", None),
+            entry("cargo build", None),
+            entry("git status", Some("web-1")),
+            entry("systemctl restart nginx", Some("web-1")),
+        ];
+        let stats = host_stats(&history, "laptop");
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].host.as_str(), stats[0].commands), ("laptop", 3));
+        assert_eq!(stats[0].top_commands[0], ("cargo build".to_string(), 2));
+        assert_eq!(stats[0].unique_commands, vec![("cargo build".to_string(), 2)]);
+        assert_eq!(stats[1].unique_commands, vec![("systemctl restart".to_string(), 1)]);
+    }
+}
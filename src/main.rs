@@ -1,15 +1,35 @@
 //! Main entry point for Heist: Cross-platform shell history analyzer
 //! Handles CLI argument parsing, shell detection, and mode switching (CLI/TUI)
 
+mod aliases;
+mod chain;
 mod cli;
+mod cwd;
+mod dangerous;
+mod durations;
+mod exit_status;
+mod hosts;
 mod parser;
 mod ui;
+mod workflows;
 mod analyzer;
 mod models;
+mod predict;
+mod privilege;
+mod projects;
+mod redact;
+mod retries;
+mod rewrite;
+mod secrets;
+mod tamper;
+mod templates;
+mod timeline;
+mod tokenizer;
+mod typos;
 
 use clap::Parser;
 use anyhow::Result;
-use crate::cli::CliArgs;
+use crate::cli::{CliArgs, Command};
 use crate::parser::{detect_shell, parse_history};
 use crate::ui::run_tui;
 use crate::analyzer::analyze_history;
@@ -21,10 +41,24 @@ fn main() -> Result<()> {
     // Detect shell type (unless overridden)
     let shell = args.shell.clone().unwrap_or_else(detect_shell);
 
+    // Commands that rewrite history files need no parsed history
+    match args.command {
+        Some(Command::Scrub(ref scrub)) => return crate::rewrite::scrub::run_scrub(&shell, scrub),
+        Some(Command::Delete(ref delete)) => return crate::rewrite::curate::run_delete(&shell, delete),
+        Some(Command::Prune(ref prune)) => return crate::rewrite::curate::run_prune(&shell, prune),
+        Some(Command::Dedup(ref target)) => return crate::rewrite::curate::run_dedup(&shell, target),
+        Some(Command::Restore(ref restore)) => return crate::rewrite::restore::run_restore(&shell, restore),
+        Some(Command::Verify(ref verify)) => return crate::chain::run_verify(verify),
+        _ => {},
+    }
+
     // Parse shell history
     let history = parse_history(&shell, &args)?;
 
-    if args.cli {
+    if let Some(Command::Predict(ref predict)) = args.command {
+        // Next-command suggestions for shell integrations
+        crate::predict::run_predict(&history, predict)?;
+    } else if args.cli {
         // Non-interactive CLI mode
         analyze_history(&history, &args)?;
     } else {
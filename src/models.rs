@@ -2,11 +2,30 @@
 use chrono::{DateTime, Local};
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct HistoryEntry {
     pub timestamp: Option<DateTime<Local>>,
     pub command: String,
     pub session_id: Option<u64>,
+    /// Exit status, when the history source records it (live tracker)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Run time in milliseconds, when recorded (zsh extended history, live tracker)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Absolute working directory, when recorded (live tracker)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Machine the command ran on, when recorded (live tracker, `~/.heist_hosts/<host>/`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Whether the command failed; `None` when the exit status is unknown
+    pub fn failed(&self) -> Option<bool> {
+        self.exit_code.map(|code| code != 0)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
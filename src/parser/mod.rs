@@ -1,6 +1,7 @@
 //! Shell history parser module
 //! Supports bash, zsh, fish, and other Unix shells
 
+use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufReader, Write as IoWrite};
 use std::path::Path;
@@ -30,20 +31,24 @@ macro_rules! log_error {
 
 /// Detect the user's shell from the SHELL environment variable
 pub fn detect_shell() -> ShellType {
-    let shell = std::env::var("SHELL").unwrap_or_default();
-    
-    match shell.as_str() {
+    shell_from_path(&std::env::var("SHELL").unwrap_or_default())
+}
+
+/// Shell type of a shell path such as `/bin/bash`
+fn shell_from_path(shell: &str) -> ShellType {
+    match shell {
         s if s.contains("zsh") => ShellType::Zsh,
         s if s.contains("fish") => ShellType::Fish,
         s if s.contains("tcsh") => ShellType::Tcsh,
         s if s.contains("csh") => ShellType::Csh,
+        // Longer names first: `mksh` contains `ksh`, `bash` contains `sh`
+        s if s.contains("mksh") => ShellType::Mksh,
         s if s.contains("ksh") => ShellType::Ksh,
         s if s.contains("dash") => ShellType::Dash,
-        s if s.contains("mksh") => ShellType::Mksh,
         s if s.contains("yash") => ShellType::Yash,
+        s if s.contains("bash") => ShellType::Bash,
         s if s.contains("osh") => ShellType::Osh,
         s if s.contains("sh") => ShellType::Sh,
-        s if s.contains("bash") => ShellType::Bash,
         _ => ShellType::Bash, // Default fallback
     }
 }
@@ -68,9 +73,19 @@ pub fn parse_history(shell: &ShellType, args: &CliArgs) -> Result<Vec<HistoryEnt
     let mut live_entries = parse_heist_live_history();
     entries.append(&mut live_entries);
 
-    // Sort and deduplicate
+    // Merge history files copied from other machines
+    let mut host_entries = parse_host_histories();
+    entries.append(&mut host_entries);
+
+    // Sort and drop entries recorded twice (e.g. by both the shell and live tracking); without
+    // timestamps, repeated commands are real re-runs
     entries.sort_by_key(|e| e.timestamp);
-    entries.dedup_by(|a, b| a.timestamp == b.timestamp && a.command == b.command);
+    entries.dedup_by(|a, b| a.timestamp.is_some() && a.timestamp == b.timestamp && a.command == b.command);
+
+    // Optionally count what the user's aliases and functions expand to
+    if args.expand_aliases {
+        crate::aliases::expand_history(&mut entries, crate::aliases::definitions(args));
+    }
 
     if entries.is_empty() {
         log_error!("No entries parsed for shell {:?}", shell);
@@ -79,6 +94,25 @@ pub fn parse_history(shell: &ShellType, args: &CliArgs) -> Result<Vec<HistoryEnt
     Ok(entries)
 }
 
+/// History file a shell writes (`None` for shells Heist cannot rewrite, like ksh's binary format)
+pub fn native_history_path(shell: &ShellType) -> Result<Option<std::path::PathBuf>> {
+    let name = match shell {
+        ShellType::Bash | ShellType::Dash | ShellType::Sh => ".bash_history",
+        ShellType::Zsh => ".zsh_history",
+        ShellType::Fish => ".local/share/fish/fish_history",
+        ShellType::Csh | ShellType::Tcsh => ".history",
+        ShellType::Yash => ".yash_history",
+        ShellType::Osh => ".osh_history",
+        ShellType::Ksh | ShellType::Mksh => return Ok(None),
+    };
+    Ok(Some(get_home_dir()?.join(name)))
+}
+
+/// The live-tracking log (`~/.heist_live_history`)
+pub fn live_history_path() -> Result<std::path::PathBuf> {
+    Ok(get_home_dir()?.join(".heist_live_history"))
+}
+
 /// Get home directory with error handling
 fn get_home_dir() -> Result<std::path::PathBuf> {
     home_dir().ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))
@@ -105,15 +139,22 @@ fn create_entry(command: String, timestamp: Option<DateTime<Local>>) -> HistoryE
         timestamp,
         command,
         session_id: None,
+        exit_code: None,
+        duration_ms: None,
+        cwd: None,
+        hostname: None,
     }
 }
 
 /// Parse bash history file (~/.bash_history)
 fn parse_bash_history(_args: &CliArgs) -> Result<Vec<HistoryEntry>> {
     let hist_path = get_home_dir()?.join(".bash_history");
-    let lines = read_history_file(&hist_path)?;
-    
-    Ok(lines
+    Ok(bash_entries(read_history_file(&hist_path)?))
+}
+
+/// Entries from bash-style lines (one command per line)
+fn bash_entries(lines: Vec<String>) -> Vec<HistoryEntry> {
+    lines
         .into_iter()
         .filter_map(|line| {
             let trimmed = line.trim();
@@ -123,16 +164,22 @@ fn parse_bash_history(_args: &CliArgs) -> Result<Vec<HistoryEntry>> {
                 Some(create_entry(trimmed.to_string(), None))
             }
         })
-        .collect())
+        .collect()
 }
 
 /// Parse zsh history file (~/.zsh_history)
 fn parse_zsh_history(_args: &CliArgs) -> Result<Vec<HistoryEntry>> {
     let hist_path = get_home_dir()?.join(".zsh_history");
-    let lines = read_history_file(&hist_path)?;
-    let re = Regex::new(r"^: (\d+):\d+;(.*)").unwrap();
-    
-    Ok(lines
+    Ok(zsh_entries(read_history_file(&hist_path)?))
+}
+
+/// Entries from zsh history lines
+fn zsh_entries(lines: Vec<String>) -> Vec<HistoryEntry> {
+    // Extended history: `: <start>:<elapsed seconds>;<command>`. With INC_APPEND_HISTORY the
+    // line is written before the command runs, so elapsed is always 0 and carries no duration.
+    let re = Regex::new(r"^: (\d+):(\d+);(.*)").unwrap();
+
+    lines
         .into_iter()
         .filter_map(|line| {
             if let Some(cap) = re.captures(&line) {
@@ -140,22 +187,27 @@ fn parse_zsh_history(_args: &CliArgs) -> Result<Vec<HistoryEntry>> {
                     .parse::<i64>()
                     .ok()
                     .and_then(|t| Local.timestamp_opt(t, 0).single());
-                let command = cap[2].trim().to_string();
-                Some(create_entry(command, timestamp))
+                let command = cap[3].trim().to_string();
+                let mut entry = create_entry(command, timestamp);
+                entry.duration_ms = cap[2].parse::<u64>().ok().filter(|&secs| secs > 0).map(|secs| secs * 1000);
+                Some(entry)
             } else if !line.trim().is_empty() {
                 Some(create_entry(line.trim().to_string(), None))
             } else {
                 None
             }
         })
-        .collect())
+        .collect()
 }
 
 /// Parse fish history file (~/.local/share/fish/fish_history)
 fn parse_fish_history(_args: &CliArgs) -> Result<Vec<HistoryEntry>> {
     let hist_path = get_home_dir()?.join(".local/share/fish/fish_history");
-    let lines = read_history_file(&hist_path)?;
-    
+    Ok(fish_entries(read_history_file(&hist_path)?))
+}
+
+/// Entries from fish history lines
+fn fish_entries(lines: Vec<String>) -> Vec<HistoryEntry> {
     let mut entries = Vec::new();
     let mut current_command = None;
     let mut current_timestamp = None;
@@ -180,7 +232,7 @@ fn parse_fish_history(_args: &CliArgs) -> Result<Vec<HistoryEntry>> {
         entries.push(create_entry(cmd, current_timestamp));
     }
     
-    Ok(entries)
+    entries
 }
 
 /// Infer timestamps for plain-text history files using file modification time
@@ -303,14 +355,168 @@ pub fn parse_heist_live_history() -> Vec<HistoryEntry> {
     BufReader::new(file)
         .lines()
         .filter_map(|line| line.ok())
-        .filter_map(|line| {
-            // Format: 2024-06-09T12:34:56+0000|command
-            let (ts_str, cmd) = line.split_once('|')?;
-            let timestamp = chrono::DateTime::parse_from_str(ts_str, "%Y-%m-%dT%H:%M:%S%z")
-                .ok()
-                .map(|dt| dt.with_timezone(&Local));
-            
-            Some(create_entry(cmd.trim().to_string(), timestamp))
-        })
+        .filter_map(|line| parse_live_line(&line))
         .collect()
-}
\ No newline at end of file
+}
+
+/// Parse history files from other machines, stored as `~/.heist_hosts/<host>/<file>`.
+/// The format follows the file name (`zsh_history`, `fish_history`, `heist_live_history`,
+/// anything else one command per line); entries are labelled with the directory name
+/// unless the record names its host.
+pub fn parse_host_histories() -> Vec<HistoryEntry> {
+    let mut entries = Vec::new();
+    for (host, path) in host_history_files() {
+        let lines = match read_history_file(&path) {
+            Ok(lines) => lines,
+            Err(e) => {
+                log_error!("Skipping {:?}: {:#}", path, e);
+                continue;
+            },
+        };
+        let mut parsed = host_file_entries(&path.file_name().unwrap_or_default().to_string_lossy(), lines);
+        for entry in &mut parsed {
+            entry.hostname.get_or_insert_with(|| host.clone());
+        }
+        entries.append(&mut parsed);
+    }
+    entries
+}
+
+/// `(host, file)` for every file under `~/.heist_hosts/<host>/`
+pub fn host_history_files() -> Vec<(String, std::path::PathBuf)> {
+    let Ok(root) = get_home_dir().map(|home| home.join(".heist_hosts")) else {
+        return Vec::new();
+    };
+    let Ok(hosts) = std::fs::read_dir(&root) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for host_dir in hosts.flatten().map(|d| d.path()).filter(|p| p.is_dir()) {
+        let host = host_dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let Ok(entries) = std::fs::read_dir(&host_dir) else { continue };
+        files.extend(entries.flatten().map(|f| f.path()).filter(|p| p.is_file()).map(|p| (host.clone(), p)));
+    }
+    files
+}
+
+/// Pick the parser for a copied history file by its name
+fn host_file_entries(file_name: &str, lines: Vec<String>) -> Vec<HistoryEntry> {
+    if file_name.contains("heist_live") {
+        lines.iter().filter_map(|line| parse_live_line(line)).collect()
+    } else if file_name.contains("zsh") {
+        zsh_entries(lines)
+    } else if file_name.contains("fish") {
+        fish_entries(lines)
+    } else {
+        bash_entries(lines)
+    }
+}
+
+/// Parse one live-tracking record.
+/// Format: `2024-06-09T12:34:56+0000|command`, optionally with percent-encoded metadata
+/// between the timestamp and the command: `2024-06-09T12:34:56+0000|@exit=1,dur=1530,cwd=/srv|command`
+/// (`dur` in milliseconds, `cwd` where the command was started, `host` the machine it ran on)
+pub fn parse_live_line(line: &str) -> Option<HistoryEntry> {
+    let (ts_str, rest) = line.split_once('|')?;
+    let timestamp = chrono::DateTime::parse_from_str(ts_str, "%Y-%m-%dT%H:%M:%S%z")
+        .ok()
+        .map(|dt| dt.with_timezone(&Local));
+    let (meta, cmd) = match rest.strip_prefix('@').and_then(|r| r.split_once('|')) {
+        Some((meta, cmd)) if meta.split(',').all(|kv| kv.contains('=')) => (parse_live_meta(meta), cmd),
+        _ => (HashMap::new(), rest),
+    };
+    let mut entry = create_entry(cmd.trim().to_string(), timestamp);
+    entry.exit_code = meta.get("exit").and_then(|v| v.parse().ok());
+    entry.duration_ms = meta.get("dur").and_then(|v| v.parse().ok());
+    entry.cwd = meta.get("cwd").filter(|v| v.starts_with('/')).cloned();
+    entry.hostname = meta.get("host").filter(|v| !v.is_empty()).cloned();
+    Some(entry)
+}
+
+/// Split `key=value,key=value` metadata, decoding `%XX` escapes in values
+fn parse_live_meta(meta: &str) -> HashMap<String, String> {
+    meta.split(',')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k.to_string(), percent_decode(v)))
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(Ok(b)) = value.get(i + 1..i + 3).map(|hex| u8::from_str_radix(hex, 16)) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_live_line() {
+        let plain = parse_live_line("2024-06-09T12:34:56+0000|echo a|b").unwrap();
+        assert_eq!(plain.command, "echo a|b");
+        assert!(plain.timestamp.is_some());
+        assert_eq!(plain.exit_code, None);
+        let meta = parse_live_line("2024-06-09T12:34:56+0000|@exit=127,dur=15,cwd=/a%2Cb|gti status").unwrap();
+        assert_eq!(meta.cwd.as_deref(), Some("/a,b"));
+        assert_eq!(meta.command, "gti status");
+        assert_eq!(meta.exit_code, Some(127));
+        assert_eq!(meta.duration_ms, Some(15));
+        // A command that merely starts with `@` is not metadata
+        assert_eq!(parse_live_line("2024-06-09T12:34:56+0000|@reboot|x").unwrap().command, "@reboot|x");
+        assert_eq!(percent_decode("a%2Cb%7Cc%"), "a,b|c%");
+        let host = parse_live_line("2024-06-09T12:34:56+0000|@exit=0,host=web-1|uptime").unwrap();
+        assert_eq!(host.hostname.as_deref(), Some("web-1"));
+    }
+
+    #[test]
+    fn test_shell_from_path() {
+        let detect = |path: &str| format!("{:?}", shell_from_path(path));
+        assert_eq!(detect("/bin/bash"), "Bash");
+        assert_eq!(detect("/usr/local/bin/bash"), "Bash");
+        assert_eq!(detect("/bin/mksh"), "Mksh");
+        assert_eq!(detect("/bin/ksh"), "Ksh");
+        assert_eq!(detect("/usr/bin/osh"), "Osh");
+        assert_eq!(detect("/bin/sh"), "Sh");
+        assert_eq!(detect("/usr/bin/zsh"), "Zsh");
+        assert_eq!(detect(""), "Bash");
+    }
+
+    #[test]
+    fn test_host_file_entries() {
+        let zsh = host_file_entries(".zsh_history", vec![": 1717936496:3;make".to_string()]);
+        assert_eq!((zsh[0].command.as_str(), zsh[0].duration_ms), ("make", Some(3000)));
+        let unknown = host_file_entries(".zsh_history", vec![": 1717936496:0;ls".to_string()]);
+        assert_eq!(unknown[0].duration_ms, None);
+        let bash = host_file_entries("bash_history", vec!["ls".to_string(), "".to_string()]);
+        assert_eq!(bash.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_history_keeps_untimed_repeats() {
+        use clap::Parser;
+        let home = std::env::temp_dir().join(format!("heist_parser_{}", std::process::id()));
+        std::fs::create_dir_all(&home).unwrap();
+        std::fs::write(home.join(".bash_history"), "make\nmake\nls\n").unwrap();
+        std::env::set_var("HOME", &home);
+        let args = CliArgs::parse_from(["heist", "--cli"]);
+        let entries = parse_history(&ShellType::Bash, &args).unwrap();
+        std::fs::remove_dir_all(&home).unwrap();
+        let commands: Vec<&str> = entries.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, ["make", "make", "ls"]);
+        let report = crate::retries::retry_report(&entries);
+        assert_eq!(report.tools[0].reruns, 1);
+    }
+}
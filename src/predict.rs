@@ -0,0 +1,267 @@
+//! Next-command prediction: interpolated n-gram model over tokenized commands
+//! Conditions on the previous two commands of the session and the working directory
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::analyzer::group_sessions;
+use crate::cli::PredictArgs;
+use crate::cwd::{home_dir_string, resolve_path, CwdTracker};
+use crate::models::HistoryEntry;
+use crate::tokenizer::tokenize;
+
+/// Interpolation weights: previous two commands, previous command, directory, overall frequency
+const WEIGHTS: [f64; 4] = [0.4, 0.3, 0.2, 0.1];
+
+/// Session gap used to reset the command context
+const SESSION_GAP_MINUTES: i64 = 10;
+
+/// A ranked suggestion
+#[derive(Debug, Clone, Serialize)]
+pub struct Prediction {
+    pub command: String,
+    pub score: f64,
+}
+
+/// Accuracy of the model on the most recent part of history, trained on the rest
+#[derive(Debug, Clone, Serialize)]
+pub struct Evaluation {
+    pub trained_on: usize,
+    pub tested_on: usize,
+    pub k: usize,
+    pub top1_accuracy: f64,
+    pub topk_accuracy: f64,
+    /// Top-1 accuracy of always suggesting the most frequent command
+    pub baseline_accuracy: f64,
+}
+
+/// Canonical form used as a model state: tokens joined by single spaces
+fn normalize(command: &str) -> String {
+    tokenize(command).join(" ")
+}
+
+/// Counts for every context the model conditions on
+#[derive(Default)]
+pub struct Predictor {
+    unigram: HashMap<String, usize>,
+    bigram: HashMap<String, HashMap<String, usize>>,
+    trigram: HashMap<(String, String), HashMap<String, usize>>,
+    by_dir: HashMap<String, HashMap<String, usize>>,
+    /// Most recent spelling of each normalized command
+    spelling: HashMap<String, String>,
+}
+
+impl Predictor {
+    /// Train on sessions of chronologically ordered commands
+    pub fn train(sessions: &[Vec<&HistoryEntry>], home: &str) -> Self {
+        let mut model = Predictor::default();
+        for session in sessions {
+            let mut previous: Vec<String> = vec![];
+            let mut dirs = CwdTracker::new(home);
+            for entry in session {
+                if let Some(ref dir) = entry.cwd {
+                    dirs.set(dir);
+                }
+                let key = normalize(&entry.command);
+                if key.is_empty() {
+                    continue;
+                }
+                model.observe(&previous, dirs.current(), &key);
+                model.spelling.insert(key.clone(), entry.command.trim().to_string());
+                dirs.observe(&entry.command);
+                previous.push(key);
+            }
+        }
+        model
+    }
+
+    fn observe(&mut self, previous: &[String], cwd: Option<&str>, key: &str) {
+        *self.unigram.entry(key.to_string()).or_insert(0) += 1;
+        if let Some(p1) = previous.last() {
+            *self.bigram.entry(p1.clone()).or_default().entry(key.to_string()).or_insert(0) += 1;
+        }
+        if let [.., p2, p1] = previous {
+            *self.trigram.entry((p2.clone(), p1.clone())).or_default().entry(key.to_string()).or_insert(0) += 1;
+        }
+        if let Some(dir) = cwd {
+            *self.by_dir.entry(dir.to_string()).or_default().entry(key.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    /// Rank next commands given the session's previous commands (oldest first), the working
+    /// directory and a typed prefix. Scores are interpolated probabilities.
+    pub fn predict(&self, previous: &[String], cwd: Option<&str>, prefix: &str, n: usize) -> Vec<Prediction> {
+        let previous: Vec<String> = previous.iter().map(|p| normalize(p)).filter(|p| !p.is_empty()).collect();
+        let contexts: [Option<&HashMap<String, usize>>; 4] = [
+            match previous.as_slice() {
+                [.., p2, p1] => self.trigram.get(&(p2.clone(), p1.clone())),
+                _ => None,
+            },
+            previous.last().and_then(|p1| self.bigram.get(p1)),
+            cwd.and_then(|d| self.by_dir.get(d)),
+            Some(&self.unigram),
+        ];
+        let mut scores: HashMap<&str, f64> = HashMap::new();
+        let mut weight_sum = 0.0;
+        for (counts, weight) in contexts.iter().zip(WEIGHTS) {
+            let Some(counts) = counts else { continue };
+            let total: usize = counts.values().sum();
+            if total == 0 {
+                continue;
+            }
+            weight_sum += weight;
+            for (key, count) in counts.iter() {
+                *scores.entry(key.as_str()).or_insert(0.0) += weight * *count as f64 / total as f64;
+            }
+        }
+        let mut ranked: Vec<Prediction> = scores.into_iter()
+            .map(|(key, score)| Prediction {
+                command: self.spelling.get(key).cloned().unwrap_or_else(|| key.to_string()),
+                score: if weight_sum > 0.0 { score / weight_sum } else { 0.0 },
+            })
+            .filter(|p| p.command.starts_with(prefix) && p.command != prefix)
+            .collect();
+        ranked.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.command.cmp(&b.command)));
+        ranked.truncate(n);
+        ranked
+    }
+}
+
+/// Train on the oldest part of history and measure top-1/top-k accuracy on the newest `holdout` share
+pub fn evaluate(history: &[HistoryEntry], holdout: f64, k: usize, home: &str) -> Evaluation {
+    let split = ((history.len() as f64) * (1.0 - holdout.clamp(0.0, 1.0))).round() as usize;
+    let (train, test) = history.split_at(split.min(history.len()));
+    let train_refs: Vec<&HistoryEntry> = train.iter().collect();
+    let model = Predictor::train(&group_sessions(&train_refs, SESSION_GAP_MINUTES), home);
+    let baseline = model.unigram.iter()
+        .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(key, _)| key.clone());
+    let test_refs: Vec<&HistoryEntry> = test.iter().collect();
+    let (mut tested, mut top1, mut topk, mut base) = (0usize, 0usize, 0usize, 0usize);
+    for session in group_sessions(&test_refs, SESSION_GAP_MINUTES) {
+        let mut previous: Vec<String> = vec![];
+        let mut dirs = CwdTracker::new(home);
+        for entry in session {
+            if let Some(ref dir) = entry.cwd {
+                dirs.set(dir);
+            }
+            let key = normalize(&entry.command);
+            if key.is_empty() {
+                continue;
+            }
+            let context = &previous[previous.len().saturating_sub(2)..];
+            let ranked = model.predict(context, dirs.current(), "", k.max(1));
+            tested += 1;
+            if ranked.first().map(|p| normalize(&p.command)) == Some(key.clone()) {
+                top1 += 1;
+            }
+            if ranked.iter().any(|p| normalize(&p.command) == key) {
+                topk += 1;
+            }
+            if baseline.as_ref() == Some(&key) {
+                base += 1;
+            }
+            dirs.observe(&entry.command);
+            previous.push(key);
+        }
+    }
+    let share = |hits: usize| if tested > 0 { hits as f64 / tested as f64 } else { 0.0 };
+    Evaluation {
+        trained_on: train.len(),
+        tested_on: tested,
+        k: k.max(1),
+        top1_accuracy: share(top1),
+        topk_accuracy: share(topk),
+        baseline_accuracy: share(base),
+    }
+}
+
+/// Print an evaluation report
+pub fn print_evaluation(eval: &Evaluation, holdout: f64) {
+    println!("\nNext-command prediction on held-out history:");
+    println!("Trained on {} commands, tested on {} (newest {:.0}%)", eval.trained_on, eval.tested_on, holdout * 100.0);
+    if eval.tested_on == 0 {
+        println!("Not enough history to evaluate.");
+        return;
+    }
+    println!("Top-1 accuracy: {:>5.1}%   (most frequent command: {:.1}%)", eval.top1_accuracy * 100.0, eval.baseline_accuracy * 100.0);
+    println!("Top-{} accuracy: {:>5.1}%", eval.k, eval.topk_accuracy * 100.0);
+}
+
+/// Entry point for `heist predict`
+pub fn run_predict(history: &[HistoryEntry], args: &PredictArgs) -> Result<()> {
+    let home = home_dir_string();
+    if args.evaluate {
+        let eval = evaluate(history, args.holdout, args.limit, &home);
+        print_evaluation(&eval, args.holdout);
+        return Ok(());
+    }
+    let refs: Vec<&HistoryEntry> = history.iter().collect();
+    let sessions = group_sessions(&refs, SESSION_GAP_MINUTES);
+    let model = Predictor::train(&sessions, &home);
+    // Without explicit context, continue from the end of the most recent session
+    let previous: Vec<String> = if args.previous.is_empty() {
+        sessions.last()
+            .map(|s| s.iter().rev().take(2).rev().map(|e| e.command.clone()).collect())
+            .unwrap_or_default()
+    } else {
+        args.previous.clone()
+    };
+    let current = std::env::current_dir().ok().map(|d| d.to_string_lossy().into_owned());
+    let cwd = match &args.cwd {
+        Some(dir) => resolve_path(current.as_deref(), dir, &home),
+        None => current,
+    };
+    let prefix = args.prefix.as_deref().unwrap_or("");
+    for prediction in model.predict(&previous, cwd.as_deref(), prefix, args.limit) {
+        if args.scores {
+            println!("{:.3}\t{}", prediction.score, prediction.command);
+        } else {
+            println!("{}", prediction.command);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(commands: &[&str]) -> Vec<HistoryEntry> {
+        commands.iter()
+            .map(|c| HistoryEntry { timestamp: None, command: c.to_string(), session_id: None, ..Default::default() })
+            .collect()
+    }
+
+    #[test]
+    fn test_predict_uses_context_and_directory() {
+        let history = entries(&[
+            "cd ~/web", "npm test", "git add .", "git commit -m 'wip'",
+            "cd ../api", "cargo build", "cargo test", "git add .", "git push",
+            "cd ~/web", "npm test", "git add .", "git commit -m 'wip'",
+        ]);
+        let refs: Vec<&HistoryEntry> = history.iter().collect();
+        let model = Predictor::train(&[refs], "/home/u");
+        let after_npm = model.predict(&["npm test".into(), "git add .".into()], Some("/home/u/web"), "", 3);
+        assert_eq!(after_npm[0].command, "git commit -m 'wip'");
+        let after_cargo = model.predict(&["cargo test".into(), "git  add  .".into()], Some("/home/u/api"), "", 3);
+        assert_eq!(after_cargo[0].command, "git push");
+        let in_api = model.predict(&[], Some("/home/u/api"), "cargo", 2);
+        assert_eq!(in_api.len(), 2);
+        assert!(in_api.iter().all(|p| p.command.starts_with("cargo")));
+    }
+
+    #[test]
+    fn test_evaluation() {
+        let cycle = ["make", "make test", "git push"];
+        let history = entries(&cycle.repeat(10));
+        let eval = evaluate(&history, 0.2, 3, "/home/u");
+        assert_eq!(eval.tested_on, 6);
+        // Only the first command of the held-out session lacks context
+        assert!((eval.top1_accuracy - 5.0 / 6.0).abs() < 1e-9);
+        assert!((eval.topk_accuracy - 1.0).abs() < f64::EPSILON);
+        assert!(eval.baseline_accuracy < eval.top1_accuracy);
+    }
+}
@@ -0,0 +1,534 @@
+//! Privilege escalation audit: commands run through `sudo`, `doas`, `su -c` and `pkexec`,
+//! the command each one wraps, root shells, commands that moved to being run privileged,
+//! and the sessions that escalated
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::models::HistoryEntry;
+use crate::tokenizer::{command_path, is_assignment, is_operator, pipelines, tokenize};
+
+/// Programs that start an interactive shell when run without `-c`
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "fish", "dash", "ksh", "mksh", "tcsh", "csh"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Escalator {
+    Sudo,
+    Doas,
+    Su,
+    Pkexec,
+}
+
+impl Escalator {
+    pub fn label(self) -> &'static str {
+        match self {
+            Escalator::Sudo => "sudo",
+            Escalator::Doas => "doas",
+            Escalator::Su => "su",
+            Escalator::Pkexec => "pkexec",
+        }
+    }
+
+    fn from_program(program: &str) -> Option<Self> {
+        match program.rsplit('/').next().unwrap_or(program) {
+            "sudo" | "sudoedit" => Some(Escalator::Sudo),
+            "doas" => Some(Escalator::Doas),
+            "su" => Some(Escalator::Su),
+            "pkexec" => Some(Escalator::Pkexec),
+            _ => None,
+        }
+    }
+
+    /// Short options that take a value (`sudo -u root`, `su -c cmd`)
+    fn value_options(self) -> &'static str {
+        match self {
+            Escalator::Sudo => "ugCDprtUTR",
+            Escalator::Doas => "uC",
+            Escalator::Su => "csgGw",
+            Escalator::Pkexec => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EscalationKind {
+    /// A single wrapped command
+    Command,
+    /// An interactive shell (`sudo -i`, `sudo su -`, `su`, `doas -s`)
+    Shell,
+    /// `sudoedit` / `sudo -e`
+    Edit,
+}
+
+/// What one pipeline stage runs with elevated privileges
+#[derive(Debug, Clone, PartialEq)]
+pub struct Escalation {
+    pub escalator: Escalator,
+    /// Target user (root unless `-u`, `--user` or a `su` user says otherwise)
+    pub user: String,
+    pub kind: EscalationKind,
+    /// The wrapped command's words; empty for shells
+    pub wrapped: Vec<String>,
+}
+
+/// Re-join words into a command line, quoting words the shell would split
+fn join_words(words: &[String]) -> String {
+    words.iter()
+        .map(|w| {
+            if is_operator(w) || !w.is_empty() && !w.contains(|c: char| c.is_whitespace() || "'\"|&;<>()$`\\".contains(c)) {
+                w.clone()
+            } else {
+                format!("'{}'", w.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parse one pipeline stage; `None` when it does not escalate (`sudo -v`, `sudo -l`, `ls`)
+pub fn parse_escalation(stage: &[String]) -> Option<Escalation> {
+    let words: Vec<&String> = stage.iter().skip_while(|t| is_assignment(t)).collect();
+    let (program, args) = words.split_first()?;
+    let escalator = Escalator::from_program(program)?;
+    let mut user = None;
+    let mut shell = false;
+    let mut edit = program.ends_with("sudoedit");
+    let mut su_command = None;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        i += 1;
+        if arg == "--" {
+            break;
+        }
+        if escalator == Escalator::Su && arg == "-" {
+            continue;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            let value_option = match name {
+                "user" | "command" | "group" | "chdir" | "host" | "prompt" | "role" | "type" | "other-user" | "command-timeout"
+                | "chroot" | "close-from" => true,
+                // `sudo --shell` is `-s`; `su --shell` names the shell to run
+                "shell" => escalator == Escalator::Su,
+                _ => false,
+            };
+            let value = match inline {
+                Some(v) => Some(v),
+                None if value_option => args.get(i).map(|v| { i += 1; v.to_string() }),
+                None => None,
+            };
+            match name {
+                "user" => user = value,
+                "command" => su_command = value,
+                "login" | "shell" if escalator == Escalator::Sudo => shell = true,
+                "edit" => edit = true,
+                "list" | "validate" | "version" | "help" => return None,
+                _ => {},
+            }
+            continue;
+        }
+        if arg.len() > 1 && arg.starts_with('-') && escalator != Escalator::Pkexec {
+            for (j, c) in arg[1..].char_indices() {
+                if escalator.value_options().contains(c) {
+                    let rest = &arg[1 + j + c.len_utf8()..];
+                    let value = if rest.is_empty() { args.get(i).map(|v| { i += 1; v.to_string() }) } else { Some(rest.to_string()) };
+                    match c {
+                        'u' => user = value,
+                        'c' => su_command = value,
+                        _ => {},
+                    }
+                    break;
+                }
+                match (escalator, c) {
+                    (Escalator::Sudo, 'i' | 's') | (Escalator::Doas, 's') => shell = true,
+                    (Escalator::Sudo, 'e') => edit = true,
+                    (Escalator::Sudo, 'l' | 'v' | 'V' | 'h' | 'K') | (Escalator::Doas, 'L') => return None,
+                    _ => {},
+                }
+            }
+            continue;
+        }
+        // `su [options] [user] [options]`: the first word is the user, not a command
+        if escalator == Escalator::Su && user.is_none() {
+            user = Some(arg.to_string());
+            continue;
+        }
+        i -= 1;
+        break;
+    }
+    let mut wrapped: Vec<String> = match su_command {
+        Some(ref command) => tokenize(command),
+        None if escalator == Escalator::Su => vec![],
+        None => args[i..].iter().map(|a| a.to_string()).collect(),
+    };
+    let user = user.unwrap_or_else(|| "root".to_string());
+    let kind = if edit {
+        EscalationKind::Edit
+    } else if wrapped.is_empty() {
+        if !shell && escalator != Escalator::Su && escalator != Escalator::Pkexec {
+            return None;
+        }
+        EscalationKind::Shell
+    } else {
+        // `sudo bash -c 'cmd'` runs cmd; `sudo bash` and `sudo su -` are shells
+        let program = wrapped[0].rsplit('/').next().unwrap_or(&wrapped[0]).to_string();
+        if SHELLS.contains(&program.as_str()) {
+            match shell_invocation(&wrapped) {
+                ShellInvocation::Command(command) => {
+                    wrapped = tokenize(&command);
+                    EscalationKind::Command
+                },
+                ShellInvocation::Script => EscalationKind::Command,
+                ShellInvocation::Interactive => {
+                    wrapped.clear();
+                    EscalationKind::Shell
+                },
+            }
+        } else if let Some(inner) = parse_escalation(&wrapped) {
+            let user = if inner.escalator == Escalator::Su || inner.user != "root" { inner.user } else { user };
+            return Some(Escalation { escalator, user, kind: inner.kind, wrapped: inner.wrapped });
+        } else {
+            EscalationKind::Command
+        }
+    };
+    Some(Escalation { escalator, user, kind, wrapped })
+}
+
+/// What a shell started through an escalator runs
+enum ShellInvocation {
+    /// `bash -c 'cmd'`, also in option clusters (`bash -lc 'cmd'`)
+    Command(String),
+    /// `bash deploy.sh`: a script or other operand
+    Script,
+    /// `bash`, `bash -l`: an interactive shell
+    Interactive,
+}
+
+/// Read a shell's options (`words[0]` is the shell) up to its first operand
+fn shell_invocation(words: &[String]) -> ShellInvocation {
+    let mut i = 1;
+    while let Some(word) = words.get(i) {
+        i += 1;
+        match word.as_str() {
+            "--" => break,
+            // Options that take the next word as their value
+            "-o" | "+o" | "-O" | "+O" | "--rcfile" | "--init-file" => i += 1,
+            w if w.starts_with("--") => {},
+            w if w.len() > 1 && w.starts_with(['-', '+']) => {
+                if w.starts_with('-') && w[1..].contains('c') {
+                    return words.get(i).map_or(ShellInvocation::Interactive, |c| ShellInvocation::Command(c.clone()));
+                }
+            },
+            _ => return ShellInvocation::Script,
+        }
+    }
+    if i < words.len() { ShellInvocation::Script } else { ShellInvocation::Interactive }
+}
+
+/// Label a wrapped or plain command by program and subcommand (`apt install`, `systemctl restart`)
+fn target_of(words: &[String]) -> String {
+    command_path(&join_words(words), 2).join(" ")
+}
+
+/// A command run through an escalator
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivilegedCommand {
+    /// 1-based position in the analysed history
+    pub entry: usize,
+    /// 1-based session number
+    pub session: usize,
+    pub timestamp: Option<DateTime<Local>>,
+    pub escalator: Escalator,
+    pub user: String,
+    pub kind: EscalationKind,
+    /// Program and subcommand of the wrapped command (`(shell)`, `(edit)` for those kinds)
+    pub target: String,
+    /// The wrapped command as resolved by the tokenizer
+    pub wrapped: String,
+    pub command: String,
+}
+
+/// How often one target command ran privileged
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetFrequency {
+    pub target: String,
+    pub count: usize,
+    pub escalators: Vec<Escalator>,
+    pub users: Vec<String>,
+    pub last: Option<DateTime<Local>>,
+}
+
+/// A command first run unprivileged and later mostly through an escalator
+#[derive(Debug, Clone, Serialize)]
+pub struct NewlyPrivileged {
+    pub target: String,
+    /// Unprivileged runs before the first privileged one
+    pub unprivileged_before: usize,
+    pub first_privileged: Option<DateTime<Local>>,
+    /// Runs from the first privileged one on
+    pub privileged_since: usize,
+    pub unprivileged_since: usize,
+}
+
+/// Runs of one target, split at its first privileged run
+#[derive(Debug, Default)]
+struct TargetRuns {
+    unprivileged_before: usize,
+    first_privileged: Option<DateTime<Local>>,
+    privileged_since: usize,
+    unprivileged_since: usize,
+    escalated: bool,
+}
+
+/// A session with at least one escalation
+#[derive(Debug, Clone, Serialize)]
+pub struct EscalatedSession {
+    pub session: usize,
+    pub start: Option<DateTime<Local>>,
+    pub end: Option<DateTime<Local>>,
+    pub commands: usize,
+    pub privileged: usize,
+    pub shells: usize,
+    /// Distinct privileged targets, in order of first use
+    pub targets: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivilegeReport {
+    pub commands: usize,
+    pub sessions: usize,
+    pub by_escalator: Vec<(Escalator, usize)>,
+    pub targets: Vec<TargetFrequency>,
+    pub newly_privileged: Vec<NewlyPrivileged>,
+    pub shells: Vec<PrivilegedCommand>,
+    pub escalated_sessions: Vec<EscalatedSession>,
+    pub privileged: Vec<PrivilegedCommand>,
+}
+
+/// Audit the escalations in already grouped sessions
+pub fn privilege_report(sessions: &[Vec<&HistoryEntry>]) -> PrivilegeReport {
+    let mut privileged: Vec<PrivilegedCommand> = vec![];
+    let mut escalated_sessions = vec![];
+    let mut runs: HashMap<String, TargetRuns> = HashMap::new();
+    let mut entry = 0;
+    for (s, session) in sessions.iter().enumerate() {
+        let mut summary = EscalatedSession {
+            session: s + 1,
+            start: session.first().and_then(|e| e.timestamp),
+            end: session.last().and_then(|e| e.timestamp),
+            commands: session.len(),
+            privileged: 0,
+            shells: 0,
+            targets: vec![],
+        };
+        for e in session {
+            entry += 1;
+            for stage in pipelines(&e.command).into_iter().flatten() {
+                let Some(esc) = parse_escalation(&stage) else {
+                    let target = target_of(&stage);
+                    if !target.is_empty() {
+                        let r = runs.entry(target).or_default();
+                        if r.escalated { r.unprivileged_since += 1 } else { r.unprivileged_before += 1 }
+                    }
+                    continue;
+                };
+                let target = match esc.kind {
+                    EscalationKind::Command => target_of(&esc.wrapped),
+                    EscalationKind::Shell => "(shell)".to_string(),
+                    EscalationKind::Edit => "(edit)".to_string(),
+                };
+                if esc.kind == EscalationKind::Command {
+                    let r = runs.entry(target.clone()).or_default();
+                    if !r.escalated {
+                        r.first_privileged = e.timestamp;
+                        r.escalated = true;
+                    }
+                    r.privileged_since += 1;
+                }
+                summary.privileged += 1;
+                if esc.kind == EscalationKind::Shell {
+                    summary.shells += 1;
+                }
+                if !summary.targets.contains(&target) {
+                    summary.targets.push(target.clone());
+                }
+                privileged.push(PrivilegedCommand {
+                    entry,
+                    session: s + 1,
+                    timestamp: e.timestamp,
+                    escalator: esc.escalator,
+                    user: esc.user,
+                    kind: esc.kind,
+                    target,
+                    wrapped: join_words(&esc.wrapped),
+                    command: e.command.clone(),
+                });
+            }
+        }
+        if summary.privileged > 0 {
+            escalated_sessions.push(summary);
+        }
+    }
+
+    let mut by_escalator: HashMap<Escalator, usize> = HashMap::new();
+    let mut targets: HashMap<&str, TargetFrequency> = HashMap::new();
+    for p in &privileged {
+        *by_escalator.entry(p.escalator).or_insert(0) += 1;
+        let t = targets.entry(p.target.as_str()).or_insert_with(|| TargetFrequency {
+            target: p.target.clone(), count: 0, escalators: vec![], users: vec![], last: None,
+        });
+        t.count += 1;
+        if !t.escalators.contains(&p.escalator) {
+            t.escalators.push(p.escalator);
+        }
+        if !t.users.contains(&p.user) {
+            t.users.push(p.user.clone());
+        }
+        t.last = t.last.max(p.timestamp);
+    }
+    let mut by_escalator: Vec<(Escalator, usize)> = by_escalator.into_iter().collect();
+    by_escalator.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let mut targets: Vec<TargetFrequency> = targets.into_values().collect();
+    targets.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.target.cmp(&b.target)));
+
+    let mut newly_privileged: Vec<NewlyPrivileged> = runs.into_iter()
+        .filter(|(_, r)| r.unprivileged_before > 0 && r.escalated && r.privileged_since > r.unprivileged_since)
+        .map(|(target, r)| NewlyPrivileged {
+            target,
+            unprivileged_before: r.unprivileged_before,
+            first_privileged: r.first_privileged,
+            privileged_since: r.privileged_since,
+            unprivileged_since: r.unprivileged_since,
+        })
+        .collect();
+    newly_privileged.sort_by(|a, b| a.first_privileged.cmp(&b.first_privileged).then_with(|| a.target.cmp(&b.target)));
+    let shells = privileged.iter().filter(|p| p.kind == EscalationKind::Shell).cloned().collect();
+
+    PrivilegeReport {
+        commands: entry,
+        sessions: sessions.len(),
+        by_escalator,
+        targets,
+        newly_privileged,
+        shells,
+        escalated_sessions,
+        privileged,
+    }
+}
+
+/// Print the audit: escalators, targets, newly privileged commands, shells, sessions and every command
+pub fn print_privilege_report(report: &PrivilegeReport) {
+    let when = |t: Option<DateTime<Local>>| t.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "-".to_string());
+    let share = |n: usize, of: usize| if of == 0 { 0.0 } else { n as f64 * 100.0 / of as f64 };
+    println!("\nPrivilege escalation ({} privileged runs in {} commands):", report.privileged.len(), report.commands);
+    if report.privileged.is_empty() {
+        println!("No sudo, doas, su or pkexec usage found.");
+        return;
+    }
+    for (escalator, n) in &report.by_escalator {
+        println!("  {:<8} {}", escalator.label(), n);
+    }
+
+    println!("\nTarget commands:");
+    println!("  {:>5}  {:<28} {:<14} {:<20} Last", "Runs", "Target", "Via", "As");
+    for t in &report.targets {
+        let via: Vec<&str> = t.escalators.iter().map(|e| e.label()).collect();
+        println!("  {:>5}  {:<28} {:<14} {:<20} {}", t.count, t.target, via.join(","), t.users.join(","), when(t.last));
+    }
+
+    println!("\nCommands that became privileged:");
+    if report.newly_privileged.is_empty() {
+        println!("  (none)");
+    }
+    for n in &report.newly_privileged {
+        println!(
+            "  {:<28} {} runs without, then privileged from {} on ({} privileged, {} without)",
+            n.target, n.unprivileged_before, when(n.first_privileged), n.privileged_since, n.unprivileged_since
+        );
+    }
+
+    println!("\nRoot shells ({}):", report.shells.len());
+    for s in &report.shells {
+        println!("  {}  session {:<4} as {:<10} {}", when(s.timestamp), s.session, s.user, s.command);
+    }
+
+    println!("\nSessions that escalated ({} of {}):", report.escalated_sessions.len(), report.sessions);
+    for s in &report.escalated_sessions {
+        println!(
+            "  session {:<4} {} → {}  {} commands, {} privileged ({:.0}%), {} shells: {}",
+            s.session, when(s.start), when(s.end), s.commands, s.privileged, share(s.privileged, s.commands), s.shells, s.targets.join(", ")
+        );
+    }
+
+    println!("\nPrivileged commands:");
+    for p in &report.privileged {
+        let wrapped = if p.wrapped.is_empty() { &p.target } else { &p.wrapped };
+        println!("  #{:<6} {}  {:<6} → {:<10} {}", p.entry, when(p.timestamp), p.escalator.label(), p.user, wrapped);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn parse(command: &str) -> Option<(Escalator, String, EscalationKind, String)> {
+        let stage = pipelines(command).into_iter().flatten().next()?;
+        parse_escalation(&stage).map(|e| (e.escalator, e.user, e.kind, join_words(&e.wrapped)))
+    }
+
+    #[test]
+    fn test_parse_escalation() {
+        use EscalationKind::*;
+        let root = || "root".to_string();
+        assert_eq!(parse("sudo apt install jq"), Some((Escalator::Sudo, root(), Command, "apt install jq".into())));
+        assert_eq!(parse("sudo -u postgres -E psql -c 'select 1'"), Some((Escalator::Sudo, "postgres".into(), Command, "psql -c 'select 1'".into())));
+        assert_eq!(parse("sudo --user=www-data -- ls /var/www"), Some((Escalator::Sudo, "www-data".into(), Command, "ls /var/www".into())));
+        assert_eq!(parse("sudo -i"), Some((Escalator::Sudo, root(), Shell, String::new())));
+        assert_eq!(parse("sudo su - postgres"), Some((Escalator::Sudo, "postgres".into(), Shell, String::new())));
+        assert_eq!(parse("sudo bash -c 'systemctl restart nginx && echo ok'").map(|p| p.3), Some("systemctl restart nginx && echo ok".into()));
+        assert_eq!(parse("sudo bash -lc 'apt update'"), Some((Escalator::Sudo, root(), Command, "apt update".into())));
+        assert_eq!(parse("sudo sh -ec 'make install'").map(|p| p.3), Some("make install".into()));
+        assert_eq!(parse("sudo bash script.sh --force"), Some((Escalator::Sudo, root(), Command, "bash script.sh --force".into())));
+        assert_eq!(parse("sudo bash -x ./deploy.sh").map(|p| p.2), Some(Command));
+        assert_eq!(parse("sudo bash -l"), Some((Escalator::Sudo, root(), Shell, String::new())));
+        assert_eq!(parse("sudo bash --rcfile /root/.rc").map(|p| p.2), Some(Shell));
+        assert_eq!(parse("su -c 'apt update' admin"), Some((Escalator::Su, "admin".into(), Command, "apt update".into())));
+        assert_eq!(parse("su -"), Some((Escalator::Su, root(), Shell, String::new())));
+        assert_eq!(parse("doas -u backup tar czf /b.tgz /etc").map(|p| (p.1, p.3)), Some(("backup".into(), "tar czf /b.tgz /etc".into())));
+        assert_eq!(parse("pkexec --user root visudo").map(|p| (p.0, p.3)), Some((Escalator::Pkexec, "visudo".into())));
+        assert_eq!(parse("sudoedit /etc/hosts").map(|p| p.2), Some(Edit));
+        assert_eq!(parse("sudo -v"), None);
+        assert_eq!(parse("sudo"), None);
+        assert_eq!(parse("LANG=C /usr/bin/sudo -n reboot").map(|p| p.3), Some("reboot".into()));
+        let stages = pipelines("echo x | sudo tee /etc/motd && sudo -k");
+        assert_eq!(stages.into_iter().flatten().filter_map(|s| parse_escalation(&s)).count(), 1);
+    }
+
+    #[test]
+    fn test_privilege_report() {
+        let at = |h: u32| Local.with_ymd_and_hms(2024, 1, 1, h, 0, 0).unwrap();
+        let history: Vec<HistoryEntry> = [
+            (9, "apt install jq"), (9, "sudo apt install jq"), (10, "ls"),
+            (15, "sudo -i"), (15, "sudo apt install fd"), (16, "doas systemctl restart nginx"),
+        ].iter().map(|(h, c)| HistoryEntry { timestamp: Some(at(*h)), command: c.to_string(), ..Default::default() }).collect();
+        let refs: Vec<&HistoryEntry> = history.iter().collect();
+        let sessions = crate::analyzer::group_sessions(&refs, 10);
+        let report = privilege_report(&sessions);
+        assert_eq!((report.commands, report.sessions, report.privileged.len()), (6, 4, 4));
+        assert_eq!((report.targets[0].target.as_str(), report.targets[0].count), ("apt install", 2));
+        assert_eq!(report.newly_privileged.len(), 1);
+        assert_eq!((report.newly_privileged[0].unprivileged_before, report.newly_privileged[0].privileged_since), (1, 2));
+        assert_eq!(report.shells.len(), 1);
+        assert_eq!(report.escalated_sessions.iter().map(|s| s.session).collect::<Vec<_>>(), vec![1, 3, 4]);
+        assert_eq!(report.by_escalator, vec![(Escalator::Sudo, 3), (Escalator::Doas, 1)]);
+    }
+}
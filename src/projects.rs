@@ -0,0 +1,226 @@
+//! Per-project analytics: maps working directories to repository roots on the local filesystem
+//! Reports time spent, top commands, build/test frequency and active days per project
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::cwd::resolve_cwds;
+use crate::models::HistoryEntry;
+use crate::tokenizer::command_path;
+
+/// Version-control roots; the outermost one wins so a monorepo counts as one project
+const VCS_MARKERS: [&str; 3] = [".git", ".hg", ".svn"];
+
+/// Build manifests; the nearest one is used outside version control
+const MANIFEST_MARKERS: [&str; 10] = [
+    "Cargo.toml", "package.json", "go.mod", "pyproject.toml", "setup.py",
+    "pom.xml", "build.gradle", "composer.json", "Gemfile", "CMakeLists.txt",
+];
+
+/// Gaps longer than this end a stretch of work
+const IDLE_MINUTES: i64 = 10;
+
+/// Finds and caches the project root of directories
+pub struct ProjectResolver {
+    home: String,
+    cache: HashMap<String, Option<String>>,
+}
+
+impl ProjectResolver {
+    pub fn new(home: &str) -> Self {
+        ProjectResolver { home: home.to_string(), cache: HashMap::new() }
+    }
+
+    /// Project root containing `dir`, if any. The home directory and `/` are never projects.
+    pub fn root(&mut self, dir: &str) -> Option<String> {
+        if let Some(root) = self.cache.get(dir) {
+            return root.clone();
+        }
+        let mut vcs: Option<&Path> = None;
+        let mut manifest: Option<&Path> = None;
+        for ancestor in Path::new(dir).ancestors() {
+            if ancestor.as_os_str() == self.home.as_str() || ancestor.parent().is_none() {
+                break;
+            }
+            if VCS_MARKERS.iter().any(|m| ancestor.join(m).exists()) {
+                vcs = Some(ancestor);
+            }
+            if manifest.is_none() && MANIFEST_MARKERS.iter().any(|m| ancestor.join(m).exists()) {
+                manifest = Some(ancestor);
+            }
+        }
+        let root = vcs.or(manifest).map(|p| p.to_string_lossy().into_owned());
+        self.cache.insert(dir.to_string(), root.clone());
+        root
+    }
+}
+
+/// Whether a command builds or tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildStep {
+    Build,
+    Test,
+}
+
+/// Classify common build and test invocations (`cargo build`, `npm test`, `make`, `pytest`)
+pub fn build_step(command: &str) -> Option<BuildStep> {
+    let path = command_path(command, 3);
+    let words: Vec<&str> = path.iter().map(|s| s.as_str()).collect();
+    match words.as_slice() {
+        ["cargo", "test", ..] | ["cargo", "nextest", ..] | ["go", "test", ..] | ["pytest", ..] | ["jest", ..]
+        | ["npm", "test", ..] | ["npm", "run", "test", ..] | ["yarn", "test", ..] | ["pnpm", "test", ..]
+        | ["make", "test", ..] | ["make", "check", ..] | ["mvn", "test", ..] | ["gradle", "test", ..]
+        | ["ctest", ..] | ["tox", ..] => Some(BuildStep::Test),
+        ["cargo", "build", ..] | ["cargo", "check", ..] | ["go", "build", ..] | ["npm", "run", "build", ..]
+        | ["yarn", "build", ..] | ["pnpm", "build", ..] | ["make", ..] | ["mvn", ..] | ["gradle", "build", ..]
+        | ["cmake", ..] | ["ninja", ..] | ["tsc", ..] => Some(BuildStep::Build),
+        _ => None,
+    }
+}
+
+/// Activity in one project
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectStats {
+    pub name: String,
+    pub root: String,
+    pub commands: usize,
+    /// Time until the next command of the session, capped at the idle gap
+    pub active_ms: u64,
+    pub builds: usize,
+    pub tests: usize,
+    pub active_days: usize,
+    pub first_day: Option<NaiveDate>,
+    pub last_day: Option<NaiveDate>,
+    pub top_commands: Vec<(String, usize)>,
+}
+
+/// Group history by project root. Entries outside any project are left out.
+pub fn project_stats(history: &[HistoryEntry], home: &str, resolver: &mut ProjectResolver) -> Vec<ProjectStats> {
+    struct Acc {
+        commands: usize,
+        active_ms: u64,
+        builds: usize,
+        tests: usize,
+        days: BTreeSet<NaiveDate>,
+        tools: HashMap<String, usize>,
+    }
+    let cwds = resolve_cwds(history, home);
+    let mut projects: HashMap<String, Acc> = HashMap::new();
+    for (i, (entry, cwd)) in history.iter().zip(&cwds).enumerate() {
+        let Some(root) = cwd.as_deref().and_then(|d| resolver.root(d)) else { continue };
+        let acc = projects.entry(root).or_insert_with(|| Acc {
+            commands: 0, active_ms: 0, builds: 0, tests: 0, days: BTreeSet::new(), tools: HashMap::new(),
+        });
+        acc.commands += 1;
+        let gap = match (entry.timestamp, history.get(i + 1).and_then(|n| n.timestamp)) {
+            (Some(ts), Some(next)) => {
+                let ms = next.signed_duration_since(ts).num_milliseconds();
+                if (0..=IDLE_MINUTES * 60_000).contains(&ms) { ms as u64 } else { 0 }
+            },
+            _ => 0,
+        };
+        acc.active_ms += gap.max(entry.duration_ms.unwrap_or(0));
+        match build_step(&entry.command) {
+            Some(BuildStep::Build) => acc.builds += 1,
+            Some(BuildStep::Test) => acc.tests += 1,
+            None => {},
+        }
+        if let Some(ts) = entry.timestamp {
+            acc.days.insert(ts.date_naive());
+        }
+        let tool = command_path(&entry.command, 2).join(" ");
+        if !tool.is_empty() {
+            *acc.tools.entry(tool).or_insert(0) += 1;
+        }
+    }
+    let mut stats: Vec<ProjectStats> = projects.into_iter()
+        .map(|(root, acc)| {
+            let mut top_commands: Vec<(String, usize)> = acc.tools.into_iter().collect();
+            top_commands.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            top_commands.truncate(5);
+            ProjectStats {
+                name: root.rsplit('/').next().unwrap_or(&root).to_string(),
+                root,
+                commands: acc.commands,
+                active_ms: acc.active_ms,
+                builds: acc.builds,
+                tests: acc.tests,
+                active_days: acc.days.len(),
+                first_day: acc.days.first().copied(),
+                last_day: acc.days.last().copied(),
+                top_commands,
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| b.active_ms.cmp(&a.active_ms).then_with(|| b.commands.cmp(&a.commands)).then_with(|| a.root.cmp(&b.root)));
+    stats
+}
+
+/// Print one block per project
+pub fn print_project_stats(stats: &[ProjectStats], home: &str) {
+    println!("\nPer-project stats ({} projects):", stats.len());
+    if stats.is_empty() {
+        println!("No commands could be placed in a project (a directory with .git, Cargo.toml, package.json, ...).");
+        return;
+    }
+    for p in stats.iter().take(15) {
+        let span = match (p.first_day, p.last_day) {
+            (Some(first), Some(last)) => format!(", {} to {}", first, last),
+            _ => String::new(),
+        };
+        println!("\n{} ({})", p.name, crate::cwd::display_dir(&p.root, home));
+        println!("  {} commands, {} active, {} active days{}", p.commands, crate::durations::format_duration(p.active_ms), p.active_days, span);
+        println!("  {} builds, {} test runs", p.builds, p.tests);
+        let top: Vec<String> = p.top_commands.iter().map(|(cmd, n)| format!("{} ({})", cmd, n)).collect();
+        println!("  Top: {}", top.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_build_step() {
+        assert_eq!(build_step("cargo test --release"), Some(BuildStep::Test));
+        assert_eq!(build_step("RUST_LOG=1 cargo build"), Some(BuildStep::Build));
+        assert_eq!(build_step("npm run build"), Some(BuildStep::Build));
+        assert_eq!(build_step("make test"), Some(BuildStep::Test));
+        assert_eq!(build_step("git status"), None);
+    }
+
+    #[test]
+    fn test_project_stats() {
+        let base = std::env::temp_dir().join(format!("heist_projects_{}", std::process::id()));
+        let home = base.join("home");
+        std::fs::create_dir_all(home.join("repo/.git")).unwrap();
+        std::fs::create_dir_all(home.join("repo/crates/core")).unwrap();
+        std::fs::write(home.join("repo/crates/core/Cargo.toml"), "").unwrap();
+        std::fs::create_dir_all(home.join("scratch")).unwrap();
+        let home_str = home.to_string_lossy().into_owned();
+        let at = |min: u32, command: &str| HistoryEntry {
+            timestamp: Local.with_ymd_and_hms(2025, 5, 5, 9, min, 0).single(),
+            command: command.to_string(),
+            ..Default::default()
+        };
+        let history = vec![
+            at(0, "cd repo/crates/core"),
+            at(1, "cargo build"),
+            at(3, "cargo test"),
+            at(4, "cd ~/scratch"),
+            at(5, "ls"),
+        ];
+        let mut resolver = ProjectResolver::new(&home_str);
+        let stats = project_stats(&history, &home_str, &mut resolver);
+        std::fs::remove_dir_all(&base).unwrap();
+        assert_eq!(stats.len(), 1);
+        let repo = &stats[0];
+        assert_eq!(repo.name, "repo");
+        assert_eq!((repo.commands, repo.builds, repo.tests, repo.active_days), (3, 1, 1, 1));
+        assert_eq!(repo.active_ms, 4 * 60_000);
+    }
+}
@@ -0,0 +1,199 @@
+//! Failure inference without exit codes: immediate re-runs, `sudo` retries and flag changes
+//! Shows which tools need the most attempts and which corrections keep recurring
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::models::HistoryEntry;
+use crate::tokenizer::{command_path, is_operator, tokenize};
+
+/// A follow-up within this many seconds counts as a retry
+const RETRY_SECONDS: i64 = 120;
+
+/// How a command was retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum RetryKind {
+    /// Same command run again
+    Rerun,
+    /// Same command prefixed with `sudo`
+    Sudo,
+    /// Same command with different flags
+    FlagChange,
+}
+
+/// Retries attributed to one tool (program and subcommand)
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolFriction {
+    pub tool: String,
+    pub runs: usize,
+    pub retries: usize,
+    pub reruns: usize,
+    pub sudo: usize,
+    pub flag_changes: usize,
+}
+
+impl ToolFriction {
+    /// Share of runs that were followed by a retry
+    pub fn retry_rate(&self) -> f64 {
+        if self.runs == 0 { 0.0 } else { self.retries as f64 / self.runs as f64 }
+    }
+}
+
+/// A recurring fix such as adding `sudo` or swapping `-xvf` for `-xzvf`
+#[derive(Debug, Clone, Serialize)]
+pub struct Correction {
+    pub tool: String,
+    pub kind: RetryKind,
+    pub change: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryReport {
+    pub retries: usize,
+    pub tools: Vec<ToolFriction>,
+    pub corrections: Vec<Correction>,
+}
+
+/// Tool a command belongs to: program and subcommand, ignoring a leading `sudo`
+fn tool_of(command: &str) -> String {
+    let command = command.trim();
+    command_path(command.strip_prefix("sudo ").unwrap_or(command), 2).join(" ")
+}
+
+/// Classify `next` as a retry of `prev`; for flag changes also return `removed → added` flags
+pub fn classify_retry(prev: &str, next: &str) -> Option<(RetryKind, String)> {
+    let (prev, next) = (prev.trim(), next.trim());
+    if prev.is_empty() || next.is_empty() {
+        return None;
+    }
+    if prev == next {
+        return Some((RetryKind::Rerun, "re-run unchanged".to_string()));
+    }
+    if !prev.starts_with("sudo ") && next.strip_prefix("sudo ").map(str::trim) == Some(prev) {
+        return Some((RetryKind::Sudo, "prefixed with sudo".to_string()));
+    }
+    let (a, b) = (tokenize(prev), tokenize(next));
+    if a.iter().any(|t| is_operator(t)) || b.iter().any(|t| is_operator(t)) {
+        return None;
+    }
+    let is_flag = |t: &&String| t.starts_with('-') && t.len() > 1;
+    let words = |tokens: &[String]| tokens.iter().filter(|t| !is_flag(t)).cloned().collect::<Vec<_>>();
+    if words(&a) != words(&b) {
+        return None;
+    }
+    let removed: Vec<&String> = a.iter().filter(is_flag).filter(|f| !b.contains(f)).collect();
+    let added: Vec<&String> = b.iter().filter(is_flag).filter(|f| !a.contains(f)).collect();
+    if removed.is_empty() && added.is_empty() {
+        return None;
+    }
+    let join = |flags: &[&String]| if flags.is_empty() {
+        "(none)".to_string()
+    } else {
+        flags.iter().map(|f| f.as_str()).collect::<Vec<_>>().join(" ")
+    };
+    Some((RetryKind::FlagChange, format!("{} → {}", join(&removed), join(&added))))
+}
+
+/// Infer retries from consecutive commands run within two minutes of each other
+pub fn retry_report(history: &[HistoryEntry]) -> RetryReport {
+    let mut tools: HashMap<String, ToolFriction> = HashMap::new();
+    for entry in history {
+        let tool = tool_of(&entry.command);
+        if tool.is_empty() {
+            continue;
+        }
+        tools.entry(tool.clone())
+            .or_insert_with(|| ToolFriction { tool, runs: 0, retries: 0, reruns: 0, sudo: 0, flag_changes: 0 })
+            .runs += 1;
+    }
+    let mut corrections: HashMap<(String, RetryKind, String), usize> = HashMap::new();
+    let mut retries = 0;
+    for pair in history.windows(2) {
+        if let (Some(a), Some(b)) = (pair[0].timestamp, pair[1].timestamp) {
+            if b.signed_duration_since(a).num_seconds() > RETRY_SECONDS {
+                continue;
+            }
+        }
+        let Some((kind, change)) = classify_retry(&pair[0].command, &pair[1].command) else { continue };
+        let tool = tool_of(&pair[0].command);
+        let Some(friction) = tools.get_mut(&tool) else { continue };
+        retries += 1;
+        friction.retries += 1;
+        match kind {
+            RetryKind::Rerun => friction.reruns += 1,
+            RetryKind::Sudo => friction.sudo += 1,
+            RetryKind::FlagChange => friction.flag_changes += 1,
+        }
+        if kind != RetryKind::Rerun {
+            *corrections.entry((tool, kind, change)).or_insert(0) += 1;
+        }
+    }
+    let mut tools: Vec<ToolFriction> = tools.into_values().filter(|t| t.retries > 0).collect();
+    tools.sort_by(|a, b| b.retries.cmp(&a.retries).then_with(|| a.tool.cmp(&b.tool)));
+    tools.truncate(20);
+    let mut corrections: Vec<Correction> = corrections.into_iter()
+        .map(|((tool, kind, change), count)| Correction { tool, kind, change, count })
+        .collect();
+    corrections.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tool.cmp(&b.tool)).then_with(|| a.change.cmp(&b.change)));
+    corrections.truncate(20);
+    RetryReport { retries, tools, corrections }
+}
+
+/// Print tools with the most retries and the most common corrections
+pub fn print_retry_report(report: &RetryReport) {
+    println!("\nInferred retries ({} total):", report.retries);
+    if report.tools.is_empty() {
+        println!("No retries found.");
+        return;
+    }
+    println!("{:<24} {:>6} {:>8} {:>6}  {:>6} {:>5} {:>6}", "Tool", "Runs", "Retries", "Rate", "Re-run", "Sudo", "Flags");
+    for t in &report.tools {
+        println!("{:<24} {:>6} {:>8} {:>5.1}%  {:>6} {:>5} {:>6}", t.tool, t.runs, t.retries, t.retry_rate() * 100.0, t.reruns, t.sudo, t.flag_changes);
+    }
+    println!("\nRecurring corrections:");
+    if report.corrections.is_empty() {
+        println!("  (none)");
+    }
+    for c in &report.corrections {
+        println!("  {:<24} {:<30} {:>4}x", c.tool, c.change, c.count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_retry() {
+        assert_eq!(classify_retry("make", "make").map(|r| r.0), Some(RetryKind::Rerun));
+        assert_eq!(classify_retry("apt install jq", "sudo apt install jq").map(|r| r.0), Some(RetryKind::Sudo));
+        assert_eq!(
+            classify_retry("tar -xvf a.tgz", "tar -xzvf a.tgz"),
+            Some((RetryKind::FlagChange, "-xvf → -xzvf".to_string()))
+        );
+        assert_eq!(
+            classify_retry("git push", "git push --force"),
+            Some((RetryKind::FlagChange, "(none) → --force".to_string()))
+        );
+        assert_eq!(classify_retry("git push", "git pull"), None);
+        assert_eq!(classify_retry("ls a", "ls b"), None);
+    }
+
+    #[test]
+    fn test_retry_report() {
+        let history: Vec<HistoryEntry> = [
+            "apt install jq", "sudo apt install jq", "cargo build", "cargo build", "cargo build",
+            "apt install fd", "sudo apt install fd", "ls",
+        ].iter().map(|c| HistoryEntry { timestamp: None, command: c.to_string(), session_id: None, ..Default::default() }).collect();
+        let report = retry_report(&history);
+        assert_eq!(report.retries, 4);
+        assert_eq!(report.tools[0].tool, "apt install");
+        assert_eq!((report.tools[0].runs, report.tools[0].sudo), (4, 2));
+        assert_eq!(report.tools[1].tool, "cargo build");
+        assert_eq!(report.tools[1].reruns, 2);
+        assert_eq!(report.corrections.len(), 1);
+        assert_eq!(report.corrections[0].count, 2);
+    }
+}
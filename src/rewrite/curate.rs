@@ -0,0 +1,123 @@
+//! `heist delete`, `heist prune` and `heist dedup`: curate history files on disk
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use super::{rewrite_files, Change, HistoryFile};
+use crate::cli::{DeleteArgs, PruneArgs, RewriteTarget, ShellType};
+use crate::tokenizer::{is_operator, tokenize};
+
+/// Programs `heist prune` removes by default when run without arguments
+pub const TRIVIAL_COMMANDS: &[&str] = &["ls", "ll", "la", "l", "cd", "pwd", "clear", "reset", "exit", "history"];
+
+/// Remove every record whose command matches `re`
+pub fn delete_matching(file: &mut HistoryFile, re: &Regex) -> Vec<Change> {
+    let format = file.format;
+    let mut changes = vec![];
+    file.records.retain(|record| {
+        if !re.is_match(&record.command(format)) {
+            return true;
+        }
+        changes.push(Change::removed(record, format, format!("matches /{}/", re.as_str())));
+        false
+    });
+    changes
+}
+
+/// A single command naming one of `programs`, with flags (`ls -la`) but no other arguments
+fn trivial(command: &str, programs: &[String]) -> bool {
+    let tokens = tokenize(command);
+    match tokens.split_first() {
+        Some((program, args)) => programs.iter().any(|p| p == program)
+            && args.iter().all(|a| a.starts_with('-') && !is_operator(a)),
+        None => false,
+    }
+}
+
+/// Remove trivial commands
+pub fn prune_trivial(file: &mut HistoryFile, programs: &[String]) -> Vec<Change> {
+    let format = file.format;
+    let mut changes = vec![];
+    file.records.retain(|record| {
+        if !trivial(&record.command(format), programs) {
+            return true;
+        }
+        changes.push(Change::removed(record, format, "trivial command".to_string()));
+        false
+    });
+    changes
+}
+
+/// Remove every record whose command appears again later in the file, so the newest copy stays
+pub fn dedup(file: &mut HistoryFile) -> Vec<Change> {
+    let format = file.format;
+    let commands: Vec<String> = file.records.iter().map(|r| r.command(format).trim().to_string()).collect();
+    let lines: Vec<usize> = file.records.iter().map(|r| r.line).collect();
+    let last: HashMap<&str, usize> = commands.iter().enumerate()
+        .filter(|(_, c)| !c.is_empty())
+        .map(|(i, c)| (c.as_str(), i))
+        .collect();
+    let mut changes = vec![];
+    let mut index = 0;
+    file.records.retain(|record| {
+        let i = index;
+        index += 1;
+        match last.get(commands[i].as_str()) {
+            Some(&newest) if newest != i => {
+                changes.push(Change::removed(record, format, format!("repeated on line {}", lines[newest])));
+                false
+            },
+            _ => true,
+        }
+    });
+    changes
+}
+
+/// Run `heist delete`
+pub fn run_delete(shell: &ShellType, args: &DeleteArgs) -> Result<()> {
+    let re = Regex::new(&args.pattern).context("Invalid regex pattern")?;
+    rewrite_files(shell, &args.target, "delete", "deleted", |file| delete_matching(file, &re))?;
+    Ok(())
+}
+
+/// Run `heist prune`
+pub fn run_prune(shell: &ShellType, args: &PruneArgs) -> Result<()> {
+    let programs: Vec<String> = if args.commands.is_empty() {
+        TRIVIAL_COMMANDS.iter().map(|c| c.to_string()).collect()
+    } else {
+        args.commands.clone()
+    };
+    rewrite_files(shell, &args.target, "prune", "pruned", |file| prune_trivial(file, &programs))?;
+    Ok(())
+}
+
+/// Run `heist dedup`
+pub fn run_dedup(shell: &ShellType, target: &RewriteTarget) -> Result<()> {
+    rewrite_files(shell, target, "dedup", "removed as duplicates", dedup)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rewrite::FileFormat;
+    use std::path::Path;
+
+    fn apply(text: &str, op: impl FnOnce(&mut HistoryFile) -> Vec<Change>) -> (String, Vec<usize>) {
+        let mut file = HistoryFile::from_bytes(Path::new("h"), FileFormat::Zsh, text.as_bytes());
+        let removed = op(&mut file).iter().map(|c| c.line).collect();
+        (String::from_utf8(file.to_bytes()).unwrap(), removed)
+    }
+
+    #[test]
+    fn test_prune_and_dedup_keep_format() {
+        let text = ": 1:0;ls -la\n: 2:0;make\n: 3:0;ls /etc\n: 4:0;cd\n: 5:0;make\n";
+        let programs: Vec<String> = TRIVIAL_COMMANDS.iter().map(|c| c.to_string()).collect();
+        assert_eq!(apply(text, |f| prune_trivial(f, &programs)), (": 2:0;make\n: 3:0;ls /etc\n: 5:0;make\n".to_string(), vec![1, 4]));
+        assert_eq!(apply(text, dedup), (": 1:0;ls -la\n: 3:0;ls /etc\n: 4:0;cd\n: 5:0;make\n".to_string(), vec![2]));
+        let re = Regex::new("^ls").unwrap();
+        assert_eq!(apply(text, |f| delete_matching(f, &re)).1, vec![1, 3]);
+    }
+}
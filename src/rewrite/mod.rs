@@ -0,0 +1,455 @@
+//! Rewriting history files in place, in each shell's own format
+//! Records keep their raw bytes, so only the lines an operation changes are re-encoded;
+//! every run backs the files up to `~/.heist_backups/<timestamp>/` first.
+
+pub mod curate;
+pub mod restore;
+pub mod scrub;
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{RewriteTarget, ShellType};
+use crate::secrets::{find_secrets, SecretMatch};
+
+/// On-disk layout of a history file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// One command per line, optionally preceded by `#<epoch>` lines (bash `HISTTIMEFORMAT`, tcsh `#+<epoch>`)
+    Plain,
+    /// `: <start>:<elapsed>;<command>`, multi-line commands continued with a trailing `\`
+    Zsh,
+    /// `- cmd: <command>` followed by indented `when:` and `paths:` lines
+    Fish,
+    /// `~/.heist_live_history`: `<timestamp>|[@meta|]<command>`
+    Live,
+}
+
+impl FileFormat {
+    /// Guess the format from a file name, like the parser does for `~/.heist_hosts`
+    pub fn from_file_name(name: &str) -> Self {
+        if name.contains("heist_live") {
+            FileFormat::Live
+        } else if name.contains("zsh") {
+            FileFormat::Zsh
+        } else if name.contains("fish") {
+            FileFormat::Fish
+        } else {
+            FileFormat::Plain
+        }
+    }
+
+    pub fn for_shell(shell: &ShellType) -> Self {
+        match shell {
+            ShellType::Zsh => FileFormat::Zsh,
+            ShellType::Fish => FileFormat::Fish,
+            _ => FileFormat::Plain,
+        }
+    }
+
+    /// Byte range of the command text within one line of a record (`index` within the record)
+    pub fn command_span(self, line: &str, index: usize) -> Option<Range<usize>> {
+        match self {
+            FileFormat::Plain => (!timestamp_line(line) && !line.trim().is_empty()).then_some(0..line.len()),
+            FileFormat::Zsh => {
+                let start = if index == 0 { zsh_header().find(line).map_or(0, |m| m.end()) } else { 0 };
+                Some(start..line.len())
+            },
+            FileFormat::Fish => line.starts_with("- cmd: ").then_some(7..line.len()),
+            FileFormat::Live => {
+                let (_, rest) = line.split_once('|')?;
+                let offset = line.len() - rest.len();
+                let skip = match rest.strip_prefix('@').and_then(|r| r.split_once('|')) {
+                    Some((meta, _)) if meta.split(',').all(|kv| kv.contains('=')) => meta.len() + 2,
+                    _ => 0,
+                };
+                Some(offset + skip..line.len())
+            },
+        }
+    }
+}
+
+fn zsh_header() -> &'static Regex {
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^: \d+:\d+;").unwrap())
+}
+
+fn timestamp_line(line: &str) -> bool {
+    line.strip_prefix('#')
+        .map(|rest| rest.strip_prefix('+').unwrap_or(rest))
+        .is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// One history entry as stored: all of its lines, without their newlines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// 1-based line number of the first line
+    pub line: usize,
+    pub lines: Vec<Vec<u8>>,
+}
+
+impl Record {
+    /// The command text, multi-line commands joined with `\n`
+    pub fn command(&self, format: FileFormat) -> String {
+        self.lines.iter().enumerate()
+            .filter_map(|(i, raw)| {
+                let line = String::from_utf8_lossy(raw);
+                format.command_span(&line, i).map(|span| line[span].to_string())
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The record's lines for a diff, with secrets shown as their kind
+    pub fn display_lines(&self, format: FileFormat) -> Vec<String> {
+        self.lines.iter().enumerate()
+            .map(|(i, raw)| {
+                let line = String::from_utf8_lossy(raw).into_owned();
+                match format.command_span(&line, i) {
+                    Some(span) => {
+                        let matches: Vec<SecretMatch> = find_secrets(&line[span.clone()]).into_iter()
+                            .map(|m| SecretMatch { start: m.start + span.start, end: m.end + span.start, ..m })
+                            .collect();
+                        describe(&line, &matches)
+                    },
+                    None => line,
+                }
+            })
+            .collect()
+    }
+}
+
+/// `line` with the given secrets replaced by their kind
+fn describe(line: &str, matches: &[SecretMatch]) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut last = 0;
+    for m in matches {
+        out.push_str(&line[last..m.start]);
+        out.push_str(&format!("[{}]", m.kind.label()));
+        last = m.end;
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
+/// A history file split into records
+#[derive(Debug, Clone)]
+pub struct HistoryFile {
+    pub path: PathBuf,
+    pub format: FileFormat,
+    pub records: Vec<Record>,
+    trailing_newline: bool,
+}
+
+impl HistoryFile {
+    pub fn read(path: &Path, format: FileFormat) -> Result<Self> {
+        let bytes = std::fs::read(path).context(format!("Failed to read history file: {:?}", path))?;
+        Ok(Self::from_bytes(path, format, &bytes))
+    }
+
+    pub fn from_bytes(path: &Path, format: FileFormat, bytes: &[u8]) -> Self {
+        let trailing_newline = bytes.ends_with(b"\n");
+        let body = if trailing_newline { &bytes[..bytes.len() - 1] } else { bytes };
+        let mut records: Vec<Record> = vec![];
+        let mut pending_timestamps: Option<Record> = None;
+        if !bytes.is_empty() {
+            for (i, raw) in body.split(|&b| b == b'\n').enumerate() {
+                let line = String::from_utf8_lossy(raw);
+                let continues = match (format, records.last()) {
+                    (FileFormat::Zsh, Some(prev)) => prev.lines.last().is_some_and(|l| l.ends_with(b"\\")),
+                    (FileFormat::Fish, Some(_)) => !line.starts_with("- "),
+                    _ => false,
+                };
+                if continues && pending_timestamps.is_none() {
+                    records.last_mut().unwrap().lines.push(raw.to_vec());
+                } else if format == FileFormat::Plain && timestamp_line(&line) {
+                    pending_timestamps.get_or_insert_with(|| Record { line: i + 1, lines: vec![] }).lines.push(raw.to_vec());
+                } else {
+                    let mut record = pending_timestamps.take().unwrap_or(Record { line: i + 1, lines: vec![] });
+                    record.lines.push(raw.to_vec());
+                    records.push(record);
+                }
+            }
+        }
+        records.extend(pending_timestamps);
+        HistoryFile { path: path.to_path_buf(), format, records, trailing_newline }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.records.iter().flat_map(|r| &r.lines).cloned().collect::<Vec<_>>().join(&b'\n');
+        if self.trailing_newline && !out.is_empty() {
+            out.push(b'\n');
+        }
+        out
+    }
+
+    /// Replace the file's contents, keeping its permissions. Written to a temporary file
+    /// and renamed so a crash never leaves a half-written history.
+    pub fn write(&self) -> Result<()> {
+        replace_file(&self.path, &self.to_bytes())
+    }
+}
+
+/// Write `bytes` to a temporary file next to `path` and rename it over `path`, keeping the
+/// permissions of the file it replaces (owner-only for a new file)
+fn replace_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = path.with_file_name(format!(".{}.heist-tmp", path.file_name().unwrap_or_default().to_string_lossy()));
+    std::fs::write(&tmp, bytes).context(format!("Failed to write {:?}", tmp))?;
+    match std::fs::metadata(path) {
+        Ok(meta) => std::fs::set_permissions(&tmp, meta.permissions()).context(format!("Failed to set permissions on {:?}", tmp))?,
+        Err(_) => restrict_permissions(&tmp, 0o600)?,
+    }
+    std::fs::rename(&tmp, path).context(format!("Failed to replace {:?}", path))
+}
+
+/// One record changed by an operation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// 1-based line number of the record
+    pub line: usize,
+    /// Why it changed, shown in the diff header
+    pub note: String,
+    /// The old lines, secrets shown as `[<kind>]` and never by value
+    pub before: Vec<String>,
+    /// The new lines; `None` when the record is removed
+    pub after: Option<Vec<String>>,
+}
+
+impl Change {
+    /// A removed record
+    pub fn removed(record: &Record, format: FileFormat, note: String) -> Self {
+        Change { line: record.line, note, before: record.display_lines(format), after: None }
+    }
+}
+
+/// Files to rewrite: `--file`, or the shell's history file and the live log
+fn targets(shell: &ShellType, file: Option<&str>) -> Result<Vec<(PathBuf, FileFormat)>> {
+    if let Some(file) = file {
+        let path = PathBuf::from(file);
+        let format = FileFormat::from_file_name(&path.file_name().unwrap_or_default().to_string_lossy());
+        return Ok(vec![(path, format)]);
+    }
+    let mut targets = vec![];
+    match crate::parser::native_history_path(shell)? {
+        Some(path) => targets.push((path, FileFormat::for_shell(shell))),
+        None => println!("Heist cannot rewrite {:?} history files; use --file for a plain-text copy.", shell),
+    }
+    targets.push((crate::parser::live_history_path()?, FileFormat::Live));
+    Ok(targets.into_iter().filter(|(path, _)| path.exists()).collect())
+}
+
+/// Print the changes to one file as a diff
+fn print_diff(name: &str, changes: &[Change]) {
+    println!("--- {}", name);
+    println!("+++ {} (rewritten)", name);
+    for c in changes {
+        println!("@@ line {}: {} @@", c.line, c.note);
+        for line in &c.before {
+            println!("-{}", line);
+        }
+        for line in c.after.iter().flatten() {
+            println!("+{}", line);
+        }
+    }
+}
+
+/// Run `op` on every target file and print its diff. With `--apply`, back the changed files up
+/// and rewrite them. Returns the backup directory when files were rewritten.
+pub fn rewrite_files(
+    shell: &ShellType,
+    target: &RewriteTarget,
+    operation: &str,
+    action: &str,
+    mut op: impl FnMut(&mut HistoryFile) -> Vec<Change>,
+) -> Result<Option<PathBuf>> {
+    let home = crate::cwd::home_dir_string();
+    let mut backup: Option<Backup> = None;
+    let mut total = 0;
+    for (path, format) in targets(shell, target.file.as_deref())? {
+        let name = crate::cwd::display_dir(&path.to_string_lossy(), &home);
+        let mut file = HistoryFile::read(&path, format)?;
+        let chained = format == FileFormat::Live && crate::chain::is_chained(&file);
+        let changes = op(&mut file);
+        if changes.is_empty() {
+            println!("{}: nothing to change", name);
+            continue;
+        }
+        print_diff(&name, &changes);
+        if chained {
+            let first = changes.iter().map(|c| c.line).min().unwrap_or(1);
+            println!("{} is hash-chained: rewriting it breaks the chain at line {}, and `heist verify` will report that.", name, first);
+        }
+        total += changes.len();
+        if target.apply {
+            let backup = match backup {
+                Some(ref mut b) => b,
+                None => backup.insert(Backup::create(operation)?),
+            };
+            backup.add(&path)?;
+            file.write()?;
+            println!("Rewrote {}: {} entries {}", name, changes.len(), action);
+        }
+        warn_shell_overwrite(format, shell);
+    }
+    if total == 0 {
+        println!("Nothing to do.");
+    } else if let Some(ref backup) = backup {
+        println!("\nBackup saved to {} (`heist restore` rolls it back).", crate::cwd::display_dir(&backup.dir.to_string_lossy(), &home));
+    } else {
+        println!("\nDry run: {} entries would be {}. Re-run with --apply to rewrite the files.", total, action);
+    }
+    Ok(backup.map(|b| b.dir))
+}
+
+/// One file saved by a backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFile {
+    pub original: PathBuf,
+    /// File name inside the backup directory
+    pub saved_as: String,
+}
+
+/// `manifest.json` of a backup directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub operation: String,
+    pub created: DateTime<Local>,
+    pub files: Vec<BackupFile>,
+}
+
+/// Copies of history files taken before a run rewrites them
+#[derive(Debug)]
+pub struct Backup {
+    pub dir: PathBuf,
+    pub manifest: BackupManifest,
+}
+
+/// `~/.heist_backups`
+pub fn backup_root() -> Result<PathBuf> {
+    dirs::home_dir()
+        .map(|h| h.join(".heist_backups"))
+        .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))
+}
+
+impl Backup {
+    /// New backup directory named after the current time; readable by the owner only,
+    /// since it holds history that may contain the secrets being scrubbed
+    pub fn create(operation: &str) -> Result<Self> {
+        let root = backup_root()?;
+        let now = Local::now();
+        let stamp = now.format("%Y%m%d-%H%M%S").to_string();
+        let mut dir = root.join(&stamp);
+        let mut n = 2;
+        while dir.exists() {
+            dir = root.join(format!("{}-{}", stamp, n));
+            n += 1;
+        }
+        std::fs::create_dir_all(&dir).context(format!("Failed to create backup directory {:?}", dir))?;
+        restrict_permissions(&root, 0o700)?;
+        restrict_permissions(&dir, 0o700)?;
+        let backup = Backup { dir, manifest: BackupManifest { operation: operation.to_string(), created: now, files: vec![] } };
+        backup.save_manifest()?;
+        Ok(backup)
+    }
+
+    /// Copy `path` into the backup before it is rewritten
+    pub fn add(&mut self, path: &Path) -> Result<()> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().trim_start_matches('.').to_string();
+        let mut saved_as = name.clone();
+        let mut n = 2;
+        while self.manifest.files.iter().any(|f| f.saved_as == saved_as) {
+            saved_as = format!("{}-{}", name, n);
+            n += 1;
+        }
+        let target = self.dir.join(&saved_as);
+        std::fs::copy(path, &target).context(format!("Failed to back up {:?}", path))?;
+        restrict_permissions(&target, 0o600)?;
+        self.manifest.files.push(BackupFile { original: path.to_path_buf(), saved_as });
+        self.save_manifest()
+    }
+
+    fn save_manifest(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.manifest).context("Failed to serialize backup manifest")?;
+        std::fs::write(self.dir.join("manifest.json"), json).context("Failed to write backup manifest")
+    }
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).context(format!("Failed to set permissions on {:?}", path))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+/// Number of running processes of `shell` owned by this user; `None` without `/proc`
+fn running_shells(shell: &str) -> Option<usize> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let uid = std::fs::metadata("/proc/self").ok()?.uid();
+        let count = std::fs::read_dir("/proc").ok()?
+            .flatten()
+            .filter(|p| p.file_name().to_string_lossy().bytes().all(|b| b.is_ascii_digit()))
+            .filter(|p| p.metadata().is_ok_and(|m| m.uid() == uid))
+            .filter(|p| std::fs::read_to_string(p.path().join("comm")).is_ok_and(|c| c.trim() == shell))
+            .count();
+        Some(count)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = shell;
+        None
+    }
+}
+
+/// Warn that open shells keep the old history in memory and may write it back
+pub fn warn_shell_overwrite(format: FileFormat, shell: &ShellType) {
+    let name = format!("{:?}", shell).to_lowercase();
+    let (name, hint) = match format {
+        FileFormat::Live => return,
+        FileFormat::Zsh => ("zsh".to_string(), "zsh saves its in-memory history on exit; close open sessions with `unset HISTFILE; exit`"),
+        FileFormat::Fish => ("fish".to_string(), "fish merges commands typed in a session when it saves; run `history merge` in open sessions"),
+        FileFormat::Plain => (name, "the shell writes its in-memory history on exit; run `history -c; history -r` in open sessions"),
+    };
+    match running_shells(&name) {
+        Some(0) => {},
+        Some(n) => println!("Warning: {} running {} session(s) may restore the old history: {}.", n, name, hint),
+        None => println!("Warning: running {} sessions may restore the old history: {}.", name, hint),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(format: FileFormat, text: &str) -> Vec<String> {
+        let file = HistoryFile::from_bytes(Path::new("h"), format, text.as_bytes());
+        assert_eq!(file.to_bytes(), text.as_bytes());
+        file.records.iter().map(|r| r.command(format)).collect()
+    }
+
+    #[test]
+    fn test_records_round_trip() {
+        assert_eq!(commands(FileFormat::Plain, "#1700000000\nls -la\ngit status\n"), vec!["ls -la", "git status"]);
+        assert_eq!(commands(FileFormat::Zsh, ": 1700000000:0;echo a \\\nb\n: 1700000005:2;make\n"), vec!["echo a \\\nb", "make"]);
+        assert_eq!(
+            commands(FileFormat::Fish, "- cmd: cargo test\n  when: 1700000000\n  paths:\n    - src\n- cmd: ls\n  when: 1700000009"),
+            vec!["cargo test", "ls"]
+        );
+        assert_eq!(
+            commands(FileFormat::Live, "2024-06-09T12:34:56+0000|@exit=1,cwd=/srv|make test\n2024-06-09T12:35:00+0000|ls\n"),
+            vec!["make test", "ls"]
+        );
+        let file = HistoryFile::from_bytes(Path::new("h"), FileFormat::Plain, b"#1700000000\nls\n");
+        assert_eq!(file.records[0].line, 1);
+    }
+}
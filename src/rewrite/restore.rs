@@ -0,0 +1,66 @@
+//! `heist restore`: roll history files back to a backup in `~/.heist_backups`
+
+use anyhow::{bail, Context, Result};
+
+use super::{backup_root, replace_file, warn_shell_overwrite, Backup, BackupManifest, FileFormat};
+use crate::cli::{RestoreArgs, ShellType};
+
+/// Backups with a readable manifest, oldest first, by directory name
+pub fn list_backups() -> Result<Vec<(String, BackupManifest)>> {
+    let root = backup_root()?;
+    let Ok(dirs) = std::fs::read_dir(&root) else {
+        return Ok(vec![]);
+    };
+    let mut backups: Vec<(String, BackupManifest)> = dirs.flatten()
+        .filter_map(|d| {
+            let text = std::fs::read_to_string(d.path().join("manifest.json")).ok()?;
+            let manifest: BackupManifest = serde_json::from_str(&text).ok()?;
+            Some((d.file_name().to_string_lossy().into_owned(), manifest))
+        })
+        .collect();
+    backups.sort_by(|a, b| a.1.created.cmp(&b.1.created).then_with(|| a.0.cmp(&b.0)));
+    Ok(backups)
+}
+
+/// Run `heist restore`
+pub fn run_restore(shell: &ShellType, args: &RestoreArgs) -> Result<()> {
+    let home = crate::cwd::home_dir_string();
+    let backups = list_backups()?;
+    if args.list {
+        if backups.is_empty() {
+            println!("No backups in {}.", crate::cwd::display_dir(&backup_root()?.to_string_lossy(), &home));
+        }
+        for (id, m) in &backups {
+            let files: Vec<String> = m.files.iter().map(|f| crate::cwd::display_dir(&f.original.to_string_lossy(), &home)).collect();
+            println!("{:<18} {}  {:<8} {}", id, m.created.format("%Y-%m-%d %H:%M:%S"), m.operation, files.join(", "));
+        }
+        return Ok(());
+    }
+    let (id, manifest) = match args.backup {
+        Some(ref wanted) => backups.iter().find(|(id, _)| id == wanted),
+        None => backups.last(),
+    }
+    .context("No such backup (see `heist restore --list`)")?;
+    if manifest.files.is_empty() {
+        bail!("Backup {} contains no files", id);
+    }
+    let dir = backup_root()?.join(id);
+
+    // The current files are backed up too, so a restore can itself be rolled back
+    let mut current = Backup::create("restore")?;
+    for f in manifest.files.iter().filter(|f| f.original.exists()) {
+        current.add(&f.original)?;
+    }
+    for f in &manifest.files {
+        let saved = std::fs::read(dir.join(&f.saved_as)).context(format!("Failed to read backup of {:?}", f.original))?;
+        replace_file(&f.original, &saved)?;
+        println!("Restored {} from {} ({})", crate::cwd::display_dir(&f.original.to_string_lossy(), &home), id, manifest.operation);
+        warn_shell_overwrite(FileFormat::from_file_name(&f.saved_as), shell);
+    }
+    println!(
+        "Commands recorded since {} are not in the restored files; the replaced files were saved to {}.",
+        manifest.created.format("%Y-%m-%d %H:%M:%S"),
+        crate::cwd::display_dir(&current.dir.to_string_lossy(), &home)
+    );
+    Ok(())
+}
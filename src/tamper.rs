@@ -0,0 +1,560 @@
+//! History tampering and gap detection: commands that clear or disable history, unexplained
+//! gaps, history files that shrank or whose mtime contradicts their content, and commands
+//! the live-tracking log and the native history disagree on
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::cli::ShellType;
+use crate::rewrite::{BackupManifest, FileFormat};
+use crate::timeline::{read_events, TimelineEvent, TimestampSource};
+use crate::tokenizer::{is_assignment, pipelines};
+
+/// History file names an attacker would delete or truncate
+const HISTORY_FILES: &[&str] = &[
+    ".bash_history", ".zsh_history", "fish_history", ".heist_live_history", ".history", ".sh_history", ".mksh_history",
+    ".yash_history", ".osh_history", "$HISTFILE", "${HISTFILE}",
+];
+
+/// Variables that turn history off when emptied or zeroed
+const HISTORY_VARIABLES: &[&str] = &["HISTFILE", "HISTSIZE", "HISTFILESIZE", "SAVEHIST", "fish_history"];
+
+/// Timestamps may step back this much (several sessions writing one file) before it counts
+const REORDER_TOLERANCE_MINUTES: i64 = 60;
+
+/// Below this many gaps, only the fixed threshold applies
+const MIN_GAPS_FOR_PERCENTILE: usize = 100;
+
+/// Earliest and latest timestamp of a period
+pub type Window = (DateTime<Local>, DateTime<Local>);
+
+/// Live and native records of the same command match within this many seconds
+const MATCH_WINDOW_SECONDS: i64 = 120;
+
+/// Newest entries of a file fingerprinted at each check, to tell rotation from removal
+const TAIL_ENTRIES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TamperKind {
+    /// `history -c`, fish `history clear`
+    ClearHistory,
+    /// `history -d N`, fish `history delete`
+    DeleteEntries,
+    /// `unset HISTFILE`, `HISTSIZE=0`, `set +o history`
+    DisableHistory,
+    /// `rm ~/.bash_history`, `> ~/.zsh_history`, `ln -sf /dev/null ~/.bash_history`
+    DestroyHistoryFile,
+    /// `kill -9 $$`: the shell exits without saving its history
+    KillShell,
+}
+
+impl TamperKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            TamperKind::ClearHistory => "cleared history",
+            TamperKind::DeleteEntries => "deleted history entries",
+            TamperKind::DisableHistory => "disabled history",
+            TamperKind::DestroyHistoryFile => "destroyed a history file",
+            TamperKind::KillShell => "killed the shell before it saved history",
+        }
+    }
+}
+
+/// Whether a path names a history file: its last component is one of `HISTORY_FILES`
+fn names_history_file(token: &str) -> bool {
+    let name = token.rsplit('/').next().unwrap_or(token);
+    HISTORY_FILES.contains(&name)
+}
+
+/// Whether an assignment (`HISTSIZE=0`, `HISTFILE=/dev/null`) turns history off
+fn disabling_assignment(token: &str) -> bool {
+    match token.split_once('=') {
+        Some((name, value)) => HISTORY_VARIABLES.contains(&name) && matches!(value, "" | "0" | "/dev/null"),
+        None => false,
+    }
+}
+
+/// How a command tampers with history, if it does
+pub fn tamper_kind(command: &str) -> Option<TamperKind> {
+    for stage in pipelines(command).into_iter().flatten() {
+        if stage.iter().take_while(|t| is_assignment(t)).any(|t| disabling_assignment(t)) && stage.iter().all(|t| is_assignment(t)) {
+            return Some(TamperKind::DisableHistory);
+        }
+        let words: Vec<&str> = stage.iter().map(String::as_str).skip_while(|t| is_assignment(t)).collect();
+        let Some((&program, args)) = words.split_first() else { continue };
+        // Redirecting into a history file truncates it, whatever the program
+        let redirected = words.windows(2).any(|w| matches!(w[0], ">" | ">|") && names_history_file(w[1]))
+            || words.iter().any(|w| w.starts_with('>') && names_history_file(w.trim_start_matches(['>', '|'])));
+        if redirected {
+            return Some(TamperKind::DestroyHistoryFile);
+        }
+        let kind = match program {
+            "history" if args.iter().any(|a| *a == "-c" || *a == "clear") => Some(TamperKind::ClearHistory),
+            "history" if args.iter().any(|a| *a == "-d" || *a == "delete") => Some(TamperKind::DeleteEntries),
+            "history" if args.iter().any(|a| *a == "-w" || *a == "-a") && args.contains(&"/dev/null") => Some(TamperKind::DisableHistory),
+            "unset" if args.iter().any(|a| HISTORY_VARIABLES.contains(a)) => Some(TamperKind::DisableHistory),
+            "export" | "declare" | "typeset" | "local" if args.iter().any(|a| disabling_assignment(a)) => Some(TamperKind::DisableHistory),
+            "set" if args.windows(2).any(|w| w == ["+o", "history"]) => Some(TamperKind::DisableHistory),
+            "set" if args.contains(&"-e") && args.contains(&"fish_history") => Some(TamperKind::DisableHistory),
+            "rm" | "shred" | "unlink" | "truncate" | "srm" | "wipe" if args.iter().any(|a| names_history_file(a)) => Some(TamperKind::DestroyHistoryFile),
+            "ln" if args.contains(&"/dev/null") && args.iter().any(|a| names_history_file(a)) => Some(TamperKind::DestroyHistoryFile),
+            "kill" if args.contains(&"$$") && args.iter().any(|a| matches!(*a, "-9" | "-KILL" | "-SIGKILL")) => Some(TamperKind::KillShell),
+            _ => None,
+        };
+        if kind.is_some() {
+            return kind;
+        }
+    }
+    None
+}
+
+/// A command that tampers with history
+#[derive(Debug, Clone, Serialize)]
+pub struct TamperCommand {
+    pub kind: TamperKind,
+    pub file: String,
+    pub line: usize,
+    pub timestamp: DateTime<Local>,
+    pub timestamp_source: TimestampSource,
+    pub command: String,
+}
+
+/// A long stretch without recorded commands in one file
+#[derive(Debug, Clone, Serialize)]
+pub struct Gap {
+    pub file: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub hours: f64,
+    /// Line of the first command after the gap
+    pub line: usize,
+    /// Commands the live log recorded during the gap
+    pub live_commands: usize,
+    /// The command right before the gap tampered with history
+    pub after_tampering: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileIssueKind {
+    /// Fewer entries than at the previous check, without the oldest ones simply rotating out
+    Shrank,
+    /// Oldest entries dropped with the newest kept, as `HISTFILESIZE` truncation does
+    Rotated,
+    /// Present at the previous check, gone now
+    Deleted,
+    /// Last modified before the newest timestamp it contains
+    MtimeBeforeContent,
+    /// Contains timestamps in the future
+    FutureTimestamp,
+    /// A timestamp far older than the one before it
+    OutOfOrder,
+}
+
+impl FileIssueKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            FileIssueKind::Shrank => "shrank",
+            FileIssueKind::Rotated => "rotated",
+            FileIssueKind::Deleted => "deleted",
+            FileIssueKind::MtimeBeforeContent => "modified before its newest entry",
+            FileIssueKind::FutureTimestamp => "timestamps in the future",
+            FileIssueKind::OutOfOrder => "timestamps out of order",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileIssue {
+    pub file: String,
+    pub kind: FileIssueKind,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DisagreementKind {
+    /// Recorded by live tracking but missing from the native history
+    MissingFromNative,
+    /// In the native history but never recorded by live tracking
+    MissingFromLive,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Disagreement {
+    pub kind: DisagreementKind,
+    pub file: String,
+    pub line: usize,
+    pub timestamp: DateTime<Local>,
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TamperReport {
+    /// Gaps longer than this many hours are reported
+    pub gap_threshold_hours: f64,
+    pub commands: Vec<TamperCommand>,
+    pub gaps: Vec<Gap>,
+    pub files: Vec<FileIssue>,
+    /// Window in which the native history and the live log are compared, if they overlap
+    pub compared: Option<Window>,
+    pub disagreements: Vec<Disagreement>,
+}
+
+/// What a history file looked like at the previous check (`~/.heist_file_state.json`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileState {
+    pub size: u64,
+    pub entries: usize,
+    pub checked: DateTime<Local>,
+    /// Fingerprint of the first entry
+    #[serde(default)]
+    pub head: String,
+    /// Fingerprint of the last `TAIL_ENTRIES` entries
+    #[serde(default)]
+    pub tail: String,
+}
+
+impl FileState {
+    pub fn new(size: u64, commands: &[&str], checked: DateTime<Local>) -> Self {
+        FileState {
+            size,
+            entries: commands.len(),
+            checked,
+            head: commands.first().map(|c| fingerprint(&[c])).unwrap_or_default(),
+            tail: fingerprint(&commands[commands.len().saturating_sub(TAIL_ENTRIES)..]),
+        }
+    }
+}
+
+/// Short hash of a run of commands
+fn fingerprint(commands: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for c in commands {
+        hasher.update(c.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher.finalize().iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn state_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".heist_file_state.json"))
+}
+
+fn load_state(path: &Path) -> BTreeMap<String, FileState> {
+    std::fs::read_to_string(path).ok().and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default()
+}
+
+/// Commands that tamper with history
+pub fn tamper_commands(events: &[TimelineEvent]) -> Vec<TamperCommand> {
+    events.iter()
+        .filter_map(|e| Some(TamperCommand {
+            kind: tamper_kind(&e.entry.command)?,
+            file: e.file.clone(),
+            line: e.line,
+            timestamp: e.timestamp,
+            timestamp_source: e.timestamp_source,
+            command: e.entry.command.clone(),
+        }))
+        .collect()
+}
+
+/// Gaps between native timestamps of one file longer than `min_hours`, or than the 99th
+/// percentile gap when there are enough of them to know the user's usual breaks
+pub fn find_gaps(events: &[TimelineEvent], live: &[TimelineEvent], min_hours: f64) -> (f64, Vec<Gap>) {
+    let native: Vec<&TimelineEvent> = events.iter().filter(|e| e.timestamp_source == TimestampSource::Native).collect();
+    let mut lengths: Vec<i64> = native.windows(2).map(|w| (w[1].timestamp - w[0].timestamp).num_seconds()).filter(|s| *s > 0).collect();
+    lengths.sort_unstable();
+    let p99 = if lengths.len() >= MIN_GAPS_FOR_PERCENTILE { lengths[lengths.len() * 99 / 100] as f64 / 3600.0 } else { 0.0 };
+    let threshold = min_hours.max(p99);
+    let gaps = native.windows(2)
+        .filter(|w| (w[1].timestamp - w[0].timestamp).num_seconds() as f64 / 3600.0 > threshold)
+        .map(|w| Gap {
+            file: w[1].file.clone(),
+            start: w[0].timestamp,
+            end: w[1].timestamp,
+            hours: (w[1].timestamp - w[0].timestamp).num_minutes() as f64 / 60.0,
+            line: w[1].line,
+            live_commands: live.iter().filter(|l| l.timestamp > w[0].timestamp && l.timestamp < w[1].timestamp).count(),
+            after_tampering: tamper_kind(&w[0].entry.command).is_some(),
+        })
+        .collect();
+    (threshold, gaps)
+}
+
+/// Content checks on one file: mtime older than its newest entry, future and out-of-order timestamps
+pub fn content_issues(file: &str, events: &[TimelineEvent], mtime: Option<DateTime<Local>>, now: DateTime<Local>) -> Vec<FileIssue> {
+    let native: Vec<&TimelineEvent> = events.iter().filter(|e| e.timestamp_source == TimestampSource::Native).collect();
+    let mut issues = vec![];
+    let issue = |kind, detail| FileIssue { file: file.to_string(), kind, detail };
+    if let (Some(newest), Some(mtime)) = (native.iter().map(|e| e.timestamp).max(), mtime) {
+        if mtime + Duration::minutes(1) < newest && newest <= now {
+            issues.push(issue(FileIssueKind::MtimeBeforeContent, format!(
+                "modified {} but contains a command from {}", mtime.format("%Y-%m-%d %H:%M"), newest.format("%Y-%m-%d %H:%M")
+            )));
+        }
+    }
+    if let Some(e) = native.iter().find(|e| e.timestamp > now + Duration::minutes(5)) {
+        issues.push(issue(FileIssueKind::FutureTimestamp, format!("line {} is dated {}", e.line, e.timestamp.format("%Y-%m-%d %H:%M"))));
+    }
+    let mut newest: Option<DateTime<Local>> = None;
+    for e in &native {
+        if let Some(prev) = newest.filter(|p| e.timestamp + Duration::minutes(REORDER_TOLERANCE_MINUTES) < *p) {
+            issues.push(issue(FileIssueKind::OutOfOrder, format!(
+                "line {} ({}) follows a command from {}", e.line, e.timestamp.format("%Y-%m-%d %H:%M"), prev.format("%Y-%m-%d %H:%M")
+            )));
+        }
+        newest = newest.max(Some(e.timestamp));
+    }
+    issues
+}
+
+/// Whether heist itself rewrote `file` (scrub, delete, prune, dedup, restore) since `since`:
+/// every rewrite leaves a backup in `~/.heist_backups`
+pub fn rewritten_by_heist(file: &Path, since: DateTime<Local>, backups: &[(String, BackupManifest)]) -> bool {
+    backups.iter().any(|(_, m)| m.created >= since && m.files.iter().any(|f| f.original == file))
+}
+
+/// Compare a file with its state at the previous check; `commands` are the file's entries now
+pub fn state_issues(file: &str, before: Option<&FileState>, now: Option<&FileState>, commands: &[&str]) -> Option<FileIssue> {
+    let before = before?;
+    let since = before.checked.format("%Y-%m-%d %H:%M");
+    let now = match now {
+        None => return Some(FileIssue { file: file.to_string(), kind: FileIssueKind::Deleted, detail: format!("had {} entries on {}", before.entries, since) }),
+        Some(now) if now.size < before.size || now.entries < before.entries => now,
+        Some(_) => return None,
+    };
+    let sizes = format!("{} → {} bytes, {} → {} entries since {}", before.size, now.size, before.entries, now.entries, since);
+    // Old state files carry no fingerprints, so every drop counts
+    let tail_len = before.entries.min(TAIL_ENTRIES);
+    let tail_kept = !before.tail.is_empty() && tail_len > 0 && commands.windows(tail_len).any(|w| fingerprint(w) == before.tail);
+    let head_kept = !before.head.is_empty() && now.head == before.head;
+    let (kind, detail) = match (tail_kept, head_kept) {
+        (true, false) => (FileIssueKind::Rotated, format!("{}; the oldest entries were dropped and the newest kept", sizes)),
+        (true, true) => (FileIssueKind::Shrank, format!("{}; entries are missing from the middle of the file", sizes)),
+        (false, _) => (FileIssueKind::Shrank, format!("{}; the newest entries of the previous check are gone", sizes)),
+    };
+    Some(FileIssue { file: file.to_string(), kind, detail })
+}
+
+/// Records of `a` with no record of the same command in `b` within a couple of minutes, inside
+/// `window`. Consecutive repeats are skipped: shells drop them with `ignoredups`.
+fn unmatched(a: &[TimelineEvent], b: &[TimelineEvent], window: Window, kind: DisagreementKind) -> Vec<Disagreement> {
+    let slack = Duration::seconds(MATCH_WINDOW_SECONDS);
+    let mut out = vec![];
+    let mut previous: Option<&str> = None;
+    for e in a {
+        let command = e.entry.command.trim();
+        let repeat = previous == Some(command);
+        previous = Some(command);
+        if repeat || e.timestamp < window.0 || e.timestamp > window.1 {
+            continue;
+        }
+        let found = b.iter().any(|o| o.entry.command.trim() == command && (o.timestamp - e.timestamp).abs() <= slack);
+        if !found {
+            out.push(Disagreement { kind, file: e.file.clone(), line: e.line, timestamp: e.timestamp, command: e.entry.command.clone() });
+        }
+    }
+    out
+}
+
+/// Commands the native history (native timestamps only) and the live log disagree on, while both were recording
+pub fn disagreements(native: &[TimelineEvent], live: &[TimelineEvent]) -> (Option<Window>, Vec<Disagreement>) {
+    let native: Vec<TimelineEvent> = native.iter().filter(|e| e.timestamp_source == TimestampSource::Native).cloned().collect();
+    let span = |events: &[TimelineEvent]| Some((events.iter().map(|e| e.timestamp).min()?, events.iter().map(|e| e.timestamp).max()?));
+    let (Some(n), Some(l)) = (span(&native), span(live)) else { return (None, vec![]) };
+    let window = (n.0.max(l.0), n.1.min(l.1));
+    if window.0 > window.1 {
+        return (None, vec![]);
+    }
+    let mut found = unmatched(live, &native, window, DisagreementKind::MissingFromNative);
+    found.extend(unmatched(&native, live, window, DisagreementKind::MissingFromLive));
+    found.sort_by_key(|d| d.timestamp);
+    (Some(window), found)
+}
+
+/// Run every check on the shell's history file and the live log; with `save_state`, record
+/// their state for the next run to compare against
+pub fn tamper_report(shell: &ShellType, min_gap_hours: f64, save_state: bool) -> Result<TamperReport> {
+    let user = crate::timeline::current_user();
+    let host = crate::hosts::local_hostname();
+    let shell_name = format!("{:?}", shell).to_lowercase();
+    let mut files: Vec<(PathBuf, Vec<TimelineEvent>)> = vec![];
+    if let Some(path) = crate::parser::native_history_path(shell)? {
+        let events = if path.exists() { read_events(&path, FileFormat::for_shell(shell), &shell_name, &user, &host) } else { vec![] };
+        files.push((path, events));
+    }
+    let live_path = crate::parser::live_history_path()?;
+    let live = if live_path.exists() { read_events(&live_path, FileFormat::Live, "heist-live", &user, &host) } else { vec![] };
+    files.push((live_path, live.clone()));
+
+    let now = Local::now();
+    let state_file = state_path().context("Could not determine home directory")?;
+    let mut state = load_state(&state_file);
+    let backups = crate::rewrite::restore::list_backups()?;
+    let mut report = TamperReport { gap_threshold_hours: min_gap_hours, commands: vec![], gaps: vec![], files: vec![], compared: None, disagreements: vec![] };
+    for (path, events) in &files {
+        let name = path.to_string_lossy().into_owned();
+        report.commands.extend(tamper_commands(events));
+        let (threshold, gaps) = find_gaps(events, if path.ends_with(".heist_live_history") { &[] } else { &live }, min_gap_hours);
+        report.gap_threshold_hours = report.gap_threshold_hours.max(threshold);
+        report.gaps.extend(gaps);
+        let commands: Vec<&str> = events.iter().map(|e| e.entry.command.as_str()).collect();
+        let current = std::fs::metadata(path).ok().map(|m| FileState::new(m.len(), &commands, now));
+        let before = state.get(&name).filter(|before| !rewritten_by_heist(path, before.checked, &backups));
+        report.files.extend(state_issues(&name, before, current.as_ref(), &commands));
+        report.files.extend(content_issues(&name, events, crate::timeline::mtime(path), now));
+        match current {
+            Some(current) => { state.insert(name, current); },
+            None => { state.remove(&name); },
+        }
+    }
+    if let [(_, native), _] = files.as_slice() {
+        (report.compared, report.disagreements) = disagreements(native, &live);
+    }
+    report.commands.sort_by_key(|c| c.timestamp);
+
+    if save_state {
+        let json = serde_json::to_string_pretty(&state).context("Failed to serialize file state")?;
+        std::fs::write(&state_file, json).context(format!("Failed to write {:?}", state_file))?;
+    }
+    Ok(report)
+}
+
+/// Print the tampering report
+pub fn print_tamper_report(report: &TamperReport) {
+    let home = crate::cwd::home_dir_string();
+    let file = |f: &str| crate::cwd::display_dir(f, &home);
+    println!("\nHistory tampering checks:");
+    println!("\nCommands that tamper with history ({}):", report.commands.len());
+    for c in &report.commands {
+        println!("  {}  {}:{}  {}: {}", c.timestamp.format("%Y-%m-%d %H:%M"), file(&c.file), c.line, c.kind.label(), c.command);
+    }
+    println!("\nGaps over {:.0} hours ({}):", report.gap_threshold_hours, report.gaps.len());
+    for g in &report.gaps {
+        let mut notes = vec![];
+        if g.live_commands > 0 {
+            notes.push(format!("live tracking recorded {} commands meanwhile", g.live_commands));
+        }
+        if g.after_tampering {
+            notes.push("right after tampering".to_string());
+        }
+        println!(
+            "  {} → {}  {:.1}h  {}:{}{}",
+            g.start.format("%Y-%m-%d %H:%M"), g.end.format("%Y-%m-%d %H:%M"), g.hours, file(&g.file), g.line,
+            if notes.is_empty() { String::new() } else { format!("  ({})", notes.join("; ")) }
+        );
+    }
+    println!("\nHistory file issues ({}):", report.files.len());
+    for f in &report.files {
+        println!("  {}  {}: {}", file(&f.file), f.kind.label(), f.detail);
+    }
+    match report.compared {
+        Some((start, end)) => {
+            println!(
+                "\nNative history vs live tracking, {} to {} ({} disagreements):",
+                start.format("%Y-%m-%d %H:%M"), end.format("%Y-%m-%d %H:%M"), report.disagreements.len()
+            );
+            for d in report.disagreements.iter().take(20) {
+                let label = match d.kind {
+                    DisagreementKind::MissingFromNative => "missing from native history",
+                    DisagreementKind::MissingFromLive => "missing from live log",
+                };
+                println!("  {}  {}:{}  {}: {}", d.timestamp.format("%Y-%m-%d %H:%M"), file(&d.file), d.line, label, d.command);
+            }
+            if report.disagreements.len() > 20 {
+                println!("  ... {} more (see --export json)", report.disagreements.len() - 20);
+            }
+        },
+        None => println!("\nNative history and live tracking do not overlap in time; no comparison."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HistoryEntry;
+    use chrono::TimeZone;
+
+    fn event(minute: i64, command: &str, file: &str) -> TimelineEvent {
+        TimelineEvent {
+            timestamp: Local.timestamp_opt(1_700_000_000 + minute * 60, 0).unwrap(),
+            timestamp_source: TimestampSource::Native,
+            user: "u".to_string(),
+            host: "h".to_string(),
+            shell: "bash".to_string(),
+            file: file.to_string(),
+            line: minute as usize,
+            entry: HistoryEntry { command: command.to_string(), ..Default::default() },
+        }
+    }
+
+    #[test]
+    fn test_tamper_kind() {
+        assert_eq!(tamper_kind("history -c && exit"), Some(TamperKind::ClearHistory));
+        assert_eq!(tamper_kind("unset HISTFILE"), Some(TamperKind::DisableHistory));
+        assert_eq!(tamper_kind("export HISTSIZE=0"), Some(TamperKind::DisableHistory));
+        assert_eq!(tamper_kind("HISTFILE=/dev/null"), Some(TamperKind::DisableHistory));
+        assert_eq!(tamper_kind("set +o history"), Some(TamperKind::DisableHistory));
+        assert_eq!(tamper_kind("rm -f ~/.bash_history"), Some(TamperKind::DestroyHistoryFile));
+        assert_eq!(tamper_kind("cat /dev/null > ~/.zsh_history"), Some(TamperKind::DestroyHistoryFile));
+        assert_eq!(tamper_kind("ln -sf /dev/null ~/.bash_history"), Some(TamperKind::DestroyHistoryFile));
+        assert_eq!(tamper_kind("kill -9 $$"), Some(TamperKind::KillShell));
+        assert_eq!(tamper_kind("history | grep ssh"), None);
+        assert_eq!(tamper_kind("HISTSIZE=0 ./script.sh"), None);
+        assert_eq!(tamper_kind("cat ~/.bash_history"), None);
+        assert_eq!(tamper_kind(">.bash_history"), Some(TamperKind::DestroyHistoryFile));
+        assert_eq!(tamper_kind("rm ~/.history"), Some(TamperKind::DestroyHistoryFile));
+        assert_eq!(tamper_kind("rm -f \"$HISTFILE\""), Some(TamperKind::DestroyHistoryFile));
+        assert_eq!(tamper_kind("rm build/session.history"), None);
+        assert_eq!(tamper_kind("echo x > notes.bash_history"), None);
+    }
+
+    #[test]
+    fn test_state_issues() {
+        let checked = Local.timestamp_opt(1_700_000_000, 0).unwrap();
+        let old: Vec<String> = (0..30).map(|i| format!("cmd {}", i)).collect();
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let before = FileState::new(300, &old, checked);
+        let check = |commands: &[&str]| {
+            let now = FileState::new(commands.len() as u64 * 10, commands, checked);
+            state_issues("f", Some(&before), Some(&now), commands).map(|i| i.kind)
+        };
+        // HISTFILESIZE keeps the newest entries, plus whatever was run since
+        assert_eq!(check(&[&old[5..], &["make"]].concat()), Some(FileIssueKind::Rotated));
+        assert_eq!(check(&[&old[..5], &old[6..]].concat()), Some(FileIssueKind::Shrank));
+        assert_eq!(check(&old[..25]), Some(FileIssueKind::Shrank));
+        assert_eq!(check(&[&old[..], &["ls"]].concat()), None);
+        assert_eq!(state_issues("f", Some(&before), None, &[]).map(|i| i.kind), Some(FileIssueKind::Deleted));
+
+        let manifest = |created| BackupManifest {
+            operation: "scrub".to_string(),
+            created,
+            files: vec![crate::rewrite::BackupFile { original: PathBuf::from("/h/.bash_history"), saved_as: "bash_history".to_string() }],
+        };
+        let backups = vec![("b".to_string(), manifest(checked + Duration::hours(1)))];
+        assert!(rewritten_by_heist(Path::new("/h/.bash_history"), checked, &backups));
+        assert!(!rewritten_by_heist(Path::new("/h/.zsh_history"), checked, &backups));
+        assert!(!rewritten_by_heist(Path::new("/h/.bash_history"), checked + Duration::hours(2), &backups));
+    }
+
+    #[test]
+    fn test_gaps_and_disagreements() {
+        let native = vec![event(0, "ls", "n"), event(1, "unset HISTFILE", "n"), event(3000, "make", "n"), event(3002, "ls", "n")];
+        let live = vec![event(0, "ls", "l"), event(1, "unset HISTFILE", "l"), event(1500, "scp secrets x:", "l"), event(3000, "make", "l")];
+        let (threshold, gaps) = find_gaps(&native, &live, 24.0);
+        assert_eq!(threshold, 24.0);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!((gaps[0].live_commands, gaps[0].after_tampering), (1, true));
+        let (window, found) = disagreements(&native, &live);
+        assert!(window.is_some());
+        let found: Vec<(DisagreementKind, &str)> = found.iter().map(|d| (d.kind, d.command.as_str())).collect();
+        assert_eq!(found, vec![(DisagreementKind::MissingFromNative, "scp secrets x:")]);
+    }
+}
@@ -0,0 +1,311 @@
+//! Parameterized function suggestions from repeated command templates
+//! Clusters commands that differ only in a few arguments and renders them as shell functions
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::aliases::{mnemonic, taken_names, unique_name, AliasDef};
+use crate::cli::AliasSyntax;
+use crate::models::HistoryEntry;
+use crate::tokenizer::{is_operator, tokenize};
+
+/// One word of a command template
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum TemplatePart {
+    /// A word shared by every command in the cluster
+    Fixed(String),
+    /// A varying word; `prefix` and `suffix` are shared by every variant
+    Param { index: usize, prefix: String, suffix: String },
+}
+
+/// A repeated command template and the function suggested for it
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandTemplate {
+    pub name: String,
+    pub parts: Vec<TemplatePart>,
+    pub params: usize,
+    /// Distinct commands matching the template
+    pub variants: usize,
+    /// Total uses of all variants
+    pub uses: usize,
+    /// Characters the function would have saved over all uses
+    pub keystrokes_saved: usize,
+}
+
+impl CommandTemplate {
+    /// Human-readable template, e.g. `ssh deploy@web<1>`
+    pub fn pattern(&self) -> String {
+        self.parts.iter().map(|part| match part {
+            TemplatePart::Fixed(word) => word.clone(),
+            TemplatePart::Param { index, prefix, suffix } => format!("{}<{}>{}", prefix, index, suffix),
+        }).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Distinct command with its tokens and number of uses
+struct Variant {
+    command: String,
+    tokens: Vec<String>,
+    count: usize,
+}
+
+/// Positions that vary within a cluster of same-length commands
+struct Cluster {
+    members: Vec<usize>,
+    variable: Vec<bool>,
+}
+
+/// Find templates shared by at least two distinct commands used three or more times in total,
+/// varying in at most `max_params` arguments and sharing more than the program name.
+/// Sorted by keystrokes saved; names avoid `reserved` executables, shell builtins and
+/// existing definitions.
+pub fn command_templates(history: &[HistoryEntry], defs: &[AliasDef], reserved: &HashSet<String>, max_params: usize) -> Vec<CommandTemplate> {
+    let mut freq: HashMap<&str, usize> = HashMap::new();
+    for entry in history {
+        *freq.entry(entry.command.trim()).or_insert(0) += 1;
+    }
+    // Group distinct commands by program and word count
+    let mut groups: HashMap<(usize, String), Vec<Variant>> = HashMap::new();
+    for (command, count) in freq {
+        let tokens = tokenize(command);
+        if tokens.len() < 2 {
+            continue;
+        }
+        groups.entry((tokens.len(), tokens[0].clone()))
+            .or_default()
+            .push(Variant { command: command.to_string(), tokens, count });
+    }
+    let mut taken = taken_names(defs, reserved);
+    let mut templates = vec![];
+    let mut keys: Vec<_> = groups.keys().cloned().collect();
+    keys.sort();
+    for key in keys {
+        let mut variants = groups.remove(&key).unwrap_or_default();
+        variants.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.command.cmp(&b.command)));
+        for cluster in cluster_variants(&variants, max_params) {
+            if let Some(template) = build_template(&variants, &cluster, &mut taken) {
+                templates.push(template);
+            }
+        }
+    }
+    templates.sort_by(|a, b| b.keystrokes_saved.cmp(&a.keystrokes_saved).then_with(|| a.name.cmp(&b.name)));
+    templates.truncate(10);
+    templates
+}
+
+/// Greedily assign each variant to the first cluster it fits without exceeding `max_params`
+/// varying positions. The program and shell operators never vary.
+fn cluster_variants(variants: &[Variant], max_params: usize) -> Vec<Cluster> {
+    let mut clusters: Vec<Cluster> = vec![];
+    for (i, variant) in variants.iter().enumerate() {
+        let joined = clusters.iter_mut().any(|cluster| {
+            let first = &variants[cluster.members[0]].tokens;
+            let variable: Vec<bool> = first.iter().zip(&variant.tokens).zip(&cluster.variable)
+                .map(|((a, b), was)| *was || a != b)
+                .collect();
+            let params = variable.iter().filter(|v| **v).count();
+            let fits = params <= max_params
+                && !variable[0]
+                && first.iter().zip(&variable).all(|(t, v)| !*v || !is_operator(t));
+            if fits {
+                cluster.members.push(i);
+                cluster.variable = variable;
+            }
+            fits
+        });
+        if !joined {
+            clusters.push(Cluster { members: vec![i], variable: vec![false; variant.tokens.len()] });
+        }
+    }
+    clusters
+}
+
+fn build_template(variants: &[Variant], cluster: &Cluster, taken: &mut HashSet<String>) -> Option<CommandTemplate> {
+    let uses: usize = cluster.members.iter().map(|&m| variants[m].count).sum();
+    if cluster.members.len() < 2 || uses < 3 {
+        return None;
+    }
+    let first = &variants[cluster.members[0]].tokens;
+    let mut parts = vec![];
+    let mut params = 0;
+    for (pos, token) in first.iter().enumerate() {
+        if !cluster.variable[pos] {
+            parts.push(TemplatePart::Fixed(token.clone()));
+            continue;
+        }
+        params += 1;
+        let values: Vec<&str> = cluster.members.iter().map(|&m| variants[m].tokens[pos].as_str()).collect();
+        let (prefix, suffix) = common_affixes(&values);
+        parts.push(TemplatePart::Param { index: params, prefix, suffix });
+    }
+    // Skip templates like `ssh <1>` that share nothing but the program
+    let shared: usize = parts[1..].iter().map(|p| match p {
+        TemplatePart::Fixed(word) => word.len(),
+        TemplatePart::Param { prefix, suffix, .. } => prefix.len() + suffix.len(),
+    }).sum();
+    if shared < 4 {
+        return None;
+    }
+    // Name after the fixed words plus the shared prefix of each parameter
+    let name_source: Vec<&str> = parts.iter().filter_map(|p| match p {
+        TemplatePart::Fixed(word) => Some(word.as_str()),
+        TemplatePart::Param { prefix, .. } if !prefix.is_empty() => Some(prefix.as_str()),
+        TemplatePart::Param { .. } => None,
+    }).collect();
+    let name = unique_name(&mnemonic(&name_source.join(" ")), taken);
+    let mut keystrokes_saved = 0;
+    for &m in &cluster.members {
+        let typed = name.len() + parts.iter().zip(&variants[m].tokens).map(|(part, token)| match part {
+            TemplatePart::Param { prefix, suffix, .. } => 1 + token.len() - prefix.len() - suffix.len(),
+            TemplatePart::Fixed(_) => 0,
+        }).sum::<usize>();
+        keystrokes_saved += variants[m].command.len().saturating_sub(typed) * variants[m].count;
+    }
+    if keystrokes_saved == 0 {
+        return None;
+    }
+    taken.insert(name.clone());
+    Some(CommandTemplate { name, parts, params, variants: cluster.members.len(), uses, keystrokes_saved })
+}
+
+/// Longest prefix and (non-overlapping) suffix shared by all values
+fn common_affixes(values: &[&str]) -> (String, String) {
+    let shortest = values.iter().map(|v| v.len()).min().unwrap_or(0);
+    let first = values[0].as_bytes();
+    let mut prefix = 0;
+    while prefix < shortest && values.iter().all(|v| v.as_bytes()[prefix] == first[prefix]) {
+        prefix += 1;
+    }
+    let mut suffix = 0;
+    while suffix < shortest - prefix
+        && values.iter().all(|v| v.as_bytes()[v.len() - 1 - suffix] == first[first.len() - 1 - suffix])
+    {
+        suffix += 1;
+    }
+    // Keep affixes on character boundaries
+    while !values[0].is_char_boundary(prefix) { prefix -= 1; }
+    while !values[0].is_char_boundary(values[0].len() - suffix) { suffix -= 1; }
+    (values[0][..prefix].to_string(), values[0][values[0].len() - suffix..].to_string())
+}
+
+/// Quote a fixed word for POSIX shells, leaving operators, redirections and plain words bare
+fn quote_posix(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-~".contains(c);
+    if is_operator(word) || word.trim_start_matches(|c: char| c.is_ascii_digit()).starts_with(['<', '>']) || word.chars().all(plain) {
+        word.to_string()
+    } else if word.contains('$') && !word.contains(['"', '`', '\\']) {
+        format!("\"{}\"", word)
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// Escape text for use inside a double-quoted string
+fn escape_double(text: &str) -> String {
+    text.replace('\\', r"\\").replace('"', "\\\"").replace('$', r"\$").replace('`', r"\`")
+}
+
+/// Render a template as a function definition in the target shell's syntax
+pub fn format_function(template: &CommandTemplate, syntax: AliasSyntax) -> String {
+    let body: Vec<String> = template.parts.iter().map(|part| match (part, syntax) {
+        (TemplatePart::Fixed(word), AliasSyntax::Nu) => {
+            if word.contains(' ') { format!("\"{}\"", escape_double(word)) } else { word.clone() }
+        },
+        (TemplatePart::Fixed(word), _) => quote_posix(word),
+        (TemplatePart::Param { index, prefix, suffix }, AliasSyntax::Fish) => {
+            format!("\"{}$argv[{}]{}\"", escape_double(prefix), index, escape_double(suffix))
+        },
+        (TemplatePart::Param { index, prefix, suffix }, AliasSyntax::Nu) => {
+            format!("$\"{}($p{}){}\"", escape_double(prefix), index, escape_double(suffix))
+        },
+        (TemplatePart::Param { index, prefix, suffix }, _) => {
+            format!("\"{}${{{}}}{}\"", escape_double(prefix), index, escape_double(suffix))
+        },
+    }).collect();
+    let body = body.join(" ");
+    match syntax {
+        AliasSyntax::Bash | AliasSyntax::Zsh => format!("{}() {{ {}; }}", template.name, body),
+        AliasSyntax::Fish => format!("function {}; {}; end", template.name, body),
+        AliasSyntax::Nu => {
+            let params: Vec<String> = (1..=template.params).map(|i| format!("p{}", i)).collect();
+            format!("def {} [{}] {{ {} }}", template.name, params.join(", "), body)
+        },
+    }
+}
+
+/// Print suggested functions with their templates and savings
+pub fn print_function_suggestions(templates: &[CommandTemplate], syntax: AliasSyntax) {
+    println!("\nFunction Suggestions (for repeated command templates):");
+    if templates.is_empty() {
+        println!("No repeated command templates found.");
+        return;
+    }
+    for t in templates {
+        println!("# {}  ({} variants, {} uses, saves ~{} keystrokes)", t.pattern(), t.variants, t.uses, t.keystrokes_saved);
+        println!("{}", format_function(t, syntax));
+    }
+}
+
+/// Write suggested functions to a file that the target shell can source
+pub fn write_function_file(path: &Path, templates: &[CommandTemplate], syntax: AliasSyntax) -> Result<()> {
+    let mut f = File::create(path).context(format!("Failed to create function file: {:?}", path))?;
+    writeln!(f, "# Functions suggested by heist ({:?} syntax)", syntax).context("Failed to write function file")?;
+    for t in templates {
+        writeln!(f, "# {}\n{}", t.pattern(), format_function(t, syntax)).context("Failed to write function file")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(commands: &[&str]) -> Vec<HistoryEntry> {
+        commands.iter()
+            .map(|c| HistoryEntry { timestamp: None, command: c.to_string(), session_id: None, ..Default::default() })
+            .collect()
+    }
+
+    #[test]
+    fn test_ssh_host_template() {
+        let h = history(&["ssh deploy@web1", "ssh deploy@web2", "ssh deploy@web2", "ssh deploy@web13"]);
+        let templates = command_templates(&h, &[], &HashSet::new(), 2);
+        assert_eq!(templates.len(), 1);
+        let t = &templates[0];
+        assert_eq!(t.pattern(), "ssh deploy@web<1>");
+        assert_eq!((t.variants, t.uses), (3, 4));
+        assert_eq!(t.name, "sd");
+        // "ssh deploy@web2" (15) vs "sd 2" (4) saves 11 per use; "...13" saves 11
+        assert_eq!(t.keystrokes_saved, 44);
+        assert_eq!(format_function(t, AliasSyntax::Bash), r#"sd() { ssh "deploy@web${1}"; }"#);
+        assert_eq!(format_function(t, AliasSyntax::Fish), r#"function sd; ssh "deploy@web$argv[1]"; end"#);
+        assert_eq!(format_function(t, AliasSyntax::Nu), r#"def sd [p1] { ssh $"deploy@web($p1)" }"#);
+    }
+
+    #[test]
+    fn test_kubectl_logs_template() {
+        let h = history(&[
+            "kubectl logs -n prod api-7f9c",
+            "kubectl logs -n prod worker-1a2b",
+            "kubectl logs -n prod api-0000",
+            "kubectl get pods -n prod",
+        ]);
+        let templates = command_templates(&h, &[], &HashSet::new(), 2);
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].pattern(), "kubectl logs -n prod <1>");
+        assert_eq!(templates[0].name, "kln");
+    }
+
+    #[test]
+    fn test_unrelated_commands_not_clustered() {
+        let h = history(&["git commit -m x", "git push origin main", "git commit -m y"]);
+        assert!(command_templates(&h, &[], &HashSet::new(), 2).is_empty());
+        let h = history(&["vim a.rs", "vim b.rs", "vim c.toml"]);
+        assert!(command_templates(&h, &[], &HashSet::new(), 2).is_empty());
+    }
+}
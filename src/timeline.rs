@@ -0,0 +1,352 @@
+//! Forensic timeline export: every command with where it came from, as a mactime bodyfile
+//! (`--export bodyfile`) or Plaso l2tcsv (`--export plaso`) for super-timelines.
+//! Timestamps are native when the file records them, interpolated between two native
+//! timestamps of the same file, and inferred (from neighbours or the file's mtime) otherwise.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Local, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::analyzer::EntryFilter;
+use crate::cli::{CliArgs, ShellType};
+use crate::models::HistoryEntry;
+use crate::redact::Redactor;
+use crate::rewrite::{FileFormat, HistoryFile, Record};
+
+// Logging macro for errors
+macro_rules! log_error {
+    ($($arg:tt)*) => {{
+        let msg = format!($($arg)*);
+        eprintln!("[heist error] {}", msg);
+        if let Ok(mut f) = OpenOptions::new().create(true).append(true).open("heist_error.log") {
+            let _ = writeln!(f, "{}", msg);
+        }
+    }};
+}
+
+/// How an event's timestamp was obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampSource {
+    /// Recorded in the history file
+    Native,
+    /// Between two recorded timestamps of the same file
+    Interpolated,
+    /// Guessed from a single neighbour or the file's modification time
+    Inferred,
+}
+
+impl TimestampSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            TimestampSource::Native => "native",
+            TimestampSource::Interpolated => "interpolated",
+            TimestampSource::Inferred => "inferred",
+        }
+    }
+}
+
+/// One command on the timeline
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEvent {
+    pub timestamp: DateTime<Local>,
+    pub timestamp_source: TimestampSource,
+    pub user: String,
+    pub host: String,
+    pub shell: String,
+    pub file: String,
+    /// 1-based line of the record in `file`
+    pub line: usize,
+    pub entry: HistoryEntry,
+}
+
+/// Timestamp a record carries itself
+fn record_timestamp(format: FileFormat, record: &Record) -> Option<DateTime<Local>> {
+    let first = String::from_utf8_lossy(record.lines.first()?).into_owned();
+    let epoch = |s: &str| s.trim().parse::<i64>().ok().and_then(|t| Local.timestamp_opt(t, 0).single());
+    match format {
+        FileFormat::Plain => first.strip_prefix('#').and_then(|t| epoch(t.trim_start_matches('+'))),
+        FileFormat::Zsh => first.strip_prefix(": ").and_then(|rest| rest.split_once(':')).and_then(|(t, _)| epoch(t)),
+        FileFormat::Fish => record.lines.iter()
+            .map(|l| String::from_utf8_lossy(l).into_owned())
+            .find_map(|l| l.strip_prefix("  when: ").and_then(epoch)),
+        FileFormat::Live => crate::parser::parse_live_line(&first).and_then(|e| e.timestamp),
+    }
+}
+
+/// Fill in missing timestamps: interpolated between two native ones, otherwise inferred one
+/// minute apart from the nearest native timestamp, or backwards from `mtime` when there is none
+pub fn assign_timestamps(native: &[Option<DateTime<Local>>], mtime: Option<DateTime<Local>>) -> Vec<Option<(DateTime<Local>, TimestampSource)>> {
+    let known: Vec<usize> = (0..native.len()).filter(|&i| native[i].is_some()).collect();
+    let last_index = native.len().saturating_sub(1);
+    (0..native.len())
+        .map(|i| {
+            if let Some(ts) = native[i] {
+                return Some((ts, TimestampSource::Native));
+            }
+            let before = known.iter().rev().find(|&&k| k < i).copied();
+            let after = known.iter().find(|&&k| k > i).copied();
+            match (before, after) {
+                (Some(b), Some(a)) => {
+                    let (tb, ta) = (native[b]?, native[a]?);
+                    let step = (ta - tb).num_milliseconds() * (i - b) as i64 / (a - b) as i64;
+                    Some((tb + Duration::milliseconds(step), TimestampSource::Interpolated))
+                },
+                (None, Some(a)) => Some((native[a]? - Duration::minutes((a - i) as i64), TimestampSource::Inferred)),
+                (Some(b), None) => {
+                    let tb = native[b]?;
+                    // Spread the tail between the last native timestamp and the file's mtime
+                    let ts = match mtime.filter(|m| *m > tb) {
+                        Some(m) => tb + Duration::milliseconds((m - tb).num_milliseconds() * (i - b) as i64 / (last_index - b) as i64),
+                        None => tb + Duration::minutes((i - b) as i64),
+                    };
+                    Some((ts, TimestampSource::Inferred))
+                },
+                (None, None) => mtime.map(|m| (m - Duration::minutes((last_index - i) as i64), TimestampSource::Inferred)),
+            }
+        })
+        .collect()
+}
+
+/// The login name: `$USER`, `$LOGNAME`, then the home directory's name
+pub fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("LOGNAME"))
+        .ok()
+        .filter(|u| !u.is_empty())
+        .or_else(|| dirs::home_dir().and_then(|h| h.file_name().map(|n| n.to_string_lossy().into_owned())))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Events from one history file
+pub fn file_events(file: &HistoryFile, shell: &str, user: &str, host: &str, mtime: Option<DateTime<Local>>) -> Vec<TimelineEvent> {
+    let records: Vec<&Record> = file.records.iter()
+        .filter(|r| !r.command(file.format).trim().is_empty())
+        .collect();
+    let native: Vec<Option<DateTime<Local>>> = records.iter().map(|r| record_timestamp(file.format, r)).collect();
+    let times = assign_timestamps(&native, mtime);
+    let path = file.path.to_string_lossy().into_owned();
+    records.iter().zip(times)
+        .filter_map(|(record, time)| {
+            let (timestamp, timestamp_source) = time?;
+            let mut entry = match file.format {
+                FileFormat::Live => crate::parser::parse_live_line(&String::from_utf8_lossy(&record.lines[0]))?,
+                _ => HistoryEntry { command: record.command(file.format).trim().to_string(), ..Default::default() },
+            };
+            entry.timestamp = Some(timestamp);
+            let host = entry.hostname.clone().unwrap_or_else(|| host.to_string());
+            Some(TimelineEvent {
+                timestamp,
+                timestamp_source,
+                user: user.to_string(),
+                host,
+                shell: shell.to_string(),
+                file: path.clone(),
+                // Plain records start with their `#<epoch>` line; the command is the last line
+                line: match file.format {
+                    FileFormat::Plain => record.line + record.lines.len().saturating_sub(1),
+                    _ => record.line,
+                },
+                entry,
+            })
+        })
+        .collect()
+}
+
+/// Last modification time of a file
+pub fn mtime(path: &Path) -> Option<DateTime<Local>> {
+    std::fs::metadata(path).ok()?.modified().ok().map(DateTime::<Local>::from)
+}
+
+/// Events from one file; unreadable files are reported and skipped
+pub fn read_events(path: &Path, format: FileFormat, shell: &str, user: &str, host: &str) -> Vec<TimelineEvent> {
+    match HistoryFile::read(path, format) {
+        Ok(file) => file_events(&file, shell, user, host, mtime(path)),
+        Err(e) => {
+            log_error!("Skipping {:?}: {:#}", path, e);
+            vec![]
+        },
+    }
+}
+
+/// Events from the shell's history file, the live log and `~/.heist_hosts`, oldest first
+pub fn collect_events(shell: &ShellType) -> Result<Vec<TimelineEvent>> {
+    let user = current_user();
+    let local = crate::hosts::local_hostname();
+    let shell_name = format!("{:?}", shell).to_lowercase();
+    let mut events = vec![];
+    if let Some(path) = crate::parser::native_history_path(shell)?.filter(|p| p.exists()) {
+        events.extend(read_events(&path, FileFormat::for_shell(shell), &shell_name, &user, &local));
+    }
+    let live = crate::parser::live_history_path()?;
+    if live.exists() {
+        events.extend(read_events(&live, FileFormat::Live, "heist-live", &user, &local));
+    }
+    for (host, path) in crate::parser::host_history_files() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let format = FileFormat::from_file_name(&name);
+        let shell = match format {
+            FileFormat::Zsh => "zsh",
+            FileFormat::Fish => "fish",
+            FileFormat::Live => "heist-live",
+            FileFormat::Plain => "bash",
+        };
+        events.extend(read_events(&path, format, shell, &user, &host));
+    }
+    events.sort_by_key(|e| e.timestamp);
+    Ok(events)
+}
+
+/// Quote a CSV field when needed
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Bodyfile fields are `|`-separated and one per line
+fn body_field(value: &str) -> String {
+    value.replace('%', "%25").replace('|', "%7C").replace('\n', "%0A")
+}
+
+/// mactime bodyfile: `MD5|name|inode|mode|UID|GID|size|atime|mtime|ctime|crtime`, with the
+/// command as the name and its time as the mtime
+pub fn write_bodyfile(events: &[TimelineEvent], out: &mut impl Write) -> Result<()> {
+    for e in events {
+        let name = format!(
+            "[{}] {}@{}: {} ({}:{}, {} timestamp)",
+            e.shell, e.user, e.host, e.entry.command, e.file, e.line, e.timestamp_source.label()
+        );
+        writeln!(out, "0|{}|0|0|0|0|0|0|{}|0|0", body_field(&name), e.timestamp.timestamp()).context("Failed to write bodyfile")?;
+    }
+    Ok(())
+}
+
+/// Plaso l2tcsv: `date,time,timezone,MACB,source,sourcetype,type,user,host,short,desc,version,filename,inode,notes,format,extra`
+pub fn write_plaso_csv(events: &[TimelineEvent], out: &mut impl Write) -> Result<()> {
+    writeln!(out, "date,time,timezone,MACB,source,sourcetype,type,user,host,short,desc,version,filename,inode,notes,format,extra")
+        .context("Failed to write CSV header")?;
+    for e in events {
+        let utc = e.timestamp.with_timezone(&Utc);
+        let short: String = e.entry.command.chars().take(80).collect();
+        let mut extra = vec![
+            format!("timestamp_source: {}", e.timestamp_source.label()),
+            format!("shell: {}", e.shell),
+            format!("line: {}", e.line),
+        ];
+        extra.extend(e.entry.exit_code.map(|c| format!("exit_code: {}", c)));
+        extra.extend(e.entry.cwd.as_ref().map(|c| format!("cwd: {}", c)));
+        let fields = [
+            utc.format("%m/%d/%Y").to_string(),
+            utc.format("%H:%M:%S").to_string(),
+            "UTC".to_string(),
+            "....".to_string(),
+            "HIST".to_string(),
+            format!("{} history", e.shell),
+            "Command run".to_string(),
+            e.user.clone(),
+            e.host.clone(),
+            short,
+            e.entry.command.clone(),
+            "2".to_string(),
+            e.file.clone(),
+            "-".to_string(),
+            "-".to_string(),
+            "heist".to_string(),
+            extra.join("; "),
+        ];
+        writeln!(out, "{}", fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",")).context("Failed to write CSV row")?;
+    }
+    Ok(())
+}
+
+/// Mask the command, directory, user, host and file of an event
+fn redact_event(redactor: &mut Redactor, e: &mut TimelineEvent) {
+    e.entry.command = redactor.redact(&e.entry.command);
+    e.entry.cwd = e.entry.cwd.as_ref().map(|c| redactor.redact(c));
+    // Same placeholder as user names in home paths
+    e.user = "[USER]".to_string();
+    e.host = redactor.redact(&e.host);
+    e.file = redactor.redact(&e.file);
+}
+
+/// `--export bodyfile|plaso`: the filtered timeline, redacted unless `--no-redact`
+pub fn export_timeline(format: &str, history: &[HistoryEntry], args: &CliArgs) -> Result<()> {
+    let shell = args.shell.clone().unwrap_or_else(crate::parser::detect_shell);
+    let filter = EntryFilter::from_args(args)?;
+    let mut events: Vec<TimelineEvent> = collect_events(&shell)?.into_iter().filter(|e| filter.matches(&e.entry)).collect();
+    let mut redactor = Redactor::from_args(args, history)?;
+    let redact = crate::redact::export_enabled(args);
+    if redact {
+        for e in &mut events {
+            redact_event(&mut redactor, e);
+        }
+    }
+    let path = if format == "bodyfile" { "heist_timeline.body" } else { "heist_timeline.csv" };
+    let mut f = std::io::BufWriter::new(std::fs::File::create(path).context("Failed to create timeline export file")?);
+    if format == "bodyfile" {
+        write_bodyfile(&events, &mut f)?;
+    } else {
+        write_plaso_csv(&events, &mut f)?;
+    }
+    f.flush().context("Failed to write timeline export")?;
+    println!("Exported {} events to {}", events.len(), path);
+    let summary = redactor.summary(redact);
+    if summary.enabled {
+        println!("Redacted {} values ({}); use --no-redact for verbatim commands.", summary.redacted.total(), summary.categories.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minute: i64) -> Option<DateTime<Local>> {
+        Local.timestamp_opt(1_700_000_000 + minute * 60, 0).single()
+    }
+
+    #[test]
+    fn test_assign_timestamps() {
+        let times = assign_timestamps(&[None, at(0), None, None, at(30), None], at(40));
+        let got: Vec<(DateTime<Local>, TimestampSource)> = times.into_iter().map(Option::unwrap).collect();
+        assert_eq!(got[0], (at(-1).unwrap(), TimestampSource::Inferred));
+        assert_eq!(got[1], (at(0).unwrap(), TimestampSource::Native));
+        assert_eq!(got[2], (at(10).unwrap(), TimestampSource::Interpolated));
+        assert_eq!(got[3], (at(20).unwrap(), TimestampSource::Interpolated));
+        assert_eq!(got[5], (at(40).unwrap(), TimestampSource::Inferred));
+        assert_eq!(assign_timestamps(&[None, None], at(5))[0], Some((at(4).unwrap(), TimestampSource::Inferred)));
+    }
+
+    #[test]
+    fn test_plaso_and_bodyfile_rows() {
+        let file = HistoryFile::from_bytes(Path::new("/home/u/.bash_history"), FileFormat::Plain, b"#1700000000\ngrep a,b x | wc -l\n");
+        let events = file_events(&file, "bash", "u", "box", None);
+        assert_eq!((events[0].line, events[0].timestamp_source), (2, TimestampSource::Native));
+        let mut csv = vec![];
+        write_plaso_csv(&events, &mut csv).unwrap();
+        let row = String::from_utf8(csv).unwrap().lines().nth(1).unwrap().to_string();
+        assert_eq!(
+            row,
+            "11/14/2023,22:13:20,UTC,....,HIST,bash history,Command run,u,box,\"grep a,b x | wc -l\",\"grep a,b x | wc -l\",2,/home/u/.bash_history,-,-,heist,timestamp_source: native; shell: bash; line: 2"
+        );
+        let mut body = vec![];
+        write_bodyfile(&events, &mut body).unwrap();
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "0|[bash] u@box: grep a,b x %7C wc -l (/home/u/.bash_history:2, native timestamp)|0|0|0|0|0|0|1700000000|0|0\n"
+        );
+
+        let mut redacted = events[0].clone();
+        redact_event(&mut Redactor::new(vec![], vec!["box".to_string()], Some("/home/u".to_string())), &mut redacted);
+        assert_eq!((redacted.user.as_str(), redacted.host.as_str(), redacted.file.as_str()), ("[USER]", "[HOST-1]", "~/.bash_history"));
+        let zsh = HistoryFile::from_bytes(Path::new("z"), FileFormat::Zsh, b": 1700000000:0;ls\n: 1700000060:0;make\n");
+        assert_eq!(file_events(&zsh, "zsh", "u", "box", None)[1].line, 2);
+    }
+}
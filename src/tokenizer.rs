@@ -0,0 +1,258 @@
+//! Lightweight shell command tokenizer
+//! Splits command lines into words without expanding variables, globs or substitutions
+
+use std::ops::Range;
+
+/// A token and the byte range it covers in the command line
+pub type Spanned = (String, Range<usize>);
+
+/// Split a command line into words, honouring single/double quotes and backslash escapes.
+/// Shell operators (`|`, `||`, `&&`, `;`, `&`) are returned as separate tokens.
+pub fn tokenize(command: &str) -> Vec<String> {
+    tokenize_spans(command).into_iter().map(|(token, _)| token).collect()
+}
+
+/// `tokenize`, with the byte range each token covers in `command` (quotes included)
+pub fn tokenize_spans(command: &str) -> Vec<Spanned> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut start: Option<usize> = None;
+    let mut chars = command.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' => {
+                start.get_or_insert(i);
+                for (_, q) in chars.by_ref() {
+                    if q == '\'' { break; }
+                    current.push(q);
+                }
+            },
+            '"' => {
+                start.get_or_insert(i);
+                while let Some((_, q)) = chars.next() {
+                    match q {
+                        '"' => break,
+                        '\\' => {
+                            if let Some((_, n)) = chars.next() { current.push(n); }
+                        },
+                        _ => current.push(q),
+                    }
+                }
+            },
+            '\\' => {
+                start.get_or_insert(i);
+                if let Some((_, n)) = chars.next() { current.push(n); }
+            },
+            // `2>&1`, `>&2` and `&>file` are redirections, not background operators
+            '&' if current.ends_with(['>', '<']) || chars.peek().map(|p| p.1) == Some('>') => {
+                start.get_or_insert(i);
+                current.push(c);
+            },
+            '|' | '&' | ';' => {
+                if let Some(s) = start.take() {
+                    tokens.push((std::mem::take(&mut current), s..i));
+                }
+                let mut op = c.to_string();
+                if (c == '|' || c == '&') && chars.peek().map(|p| p.1) == Some(c) {
+                    op.push(c);
+                    chars.next();
+                }
+                let end = i + op.len();
+                tokens.push((op, i..end));
+            },
+            c if c.is_whitespace() => {
+                if let Some(s) = start.take() {
+                    tokens.push((std::mem::take(&mut current), s..i));
+                }
+            },
+            _ => {
+                start.get_or_insert(i);
+                current.push(c);
+            },
+        }
+    }
+    if let Some(s) = start {
+        tokens.push((current, s..command.len()));
+    }
+    tokens
+}
+
+/// Whether a token is a shell control operator produced by `tokenize`
+pub fn is_operator(token: &str) -> bool {
+    matches!(token, "|" | "||" | "&&" | ";" | "&")
+}
+
+/// Whether a token is a leading `NAME=value` environment assignment
+pub fn is_assignment(token: &str) -> bool {
+    match token.split_once('=') {
+        Some((name, _)) => !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !name.starts_with(|c: char| c.is_ascii_digit()),
+        None => false,
+    }
+}
+
+/// Programs whose arguments are files, directories or text, never subcommands
+const OPERAND_PROGRAMS: &[&str] = &[
+    "cat", "cd", "pushd", "ls", "ll", "la", "less", "more", "head", "tail", "vim", "vi", "nvim", "nano", "emacs", "code",
+    "open", "xdg-open", "rm", "cp", "mv", "mkdir", "rmdir", "touch", "chmod", "chown", "ln", "file", "stat", "wc",
+    "echo", "printf", "source", ".", "man", "which", "type", "bat", "du", "tar", "unzip", "grep", "rg", "find",
+];
+
+/// Interpreters and remote shells: their first argument is a script, module or host
+const SCRIPT_PROGRAMS: &[&str] = &[
+    "python", "python2", "python3", "pypy", "pypy3", "node", "ruby", "perl", "php", "lua", "Rscript", "bash", "sh", "zsh",
+    "ssh", "scp", "sftp", "rsync", "mosh", "telnet",
+];
+
+/// Programs (or program and subcommand) whose subcommands have subcommands of their own;
+/// everything else stops at the first subcommand (`git checkout feature-x`, `npm install lodash`)
+const NESTED_COMMANDS: &[&str] = &[
+    "docker", "podman", "kubectl", "helm", "aws", "gcloud", "az", "gh", "terraform",
+    "git remote", "git stash", "git submodule", "git worktree", "npm run", "pnpm run", "yarn run",
+];
+
+/// Whether a program never takes subcommands
+fn takes_operands(program: &str) -> bool {
+    OPERAND_PROGRAMS.contains(&program)
+        || SCRIPT_PROGRAMS.contains(&program)
+        || program.strip_prefix("python").is_some_and(|v| v.chars().all(|c| c.is_ascii_digit() || c == '.'))
+}
+
+/// Whether a token looks like a subcommand word (`commit`, `get`, `build-std`): lowercase,
+/// no path separators, extensions or option dashes
+fn is_subcommand_word(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_lowercase())
+        && token.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_' || c == ':')
+}
+
+/// Program and subcommand path of the first command in a line, up to `depth` words.
+/// `git commit -m "x"` at depth 3 yields `["git", "commit"]`: descent stops at the first flag,
+/// path or operator, and goes past the first subcommand only for `NESTED_COMMANDS`.
+pub fn command_path(command: &str, depth: usize) -> Vec<String> {
+    let tokens = tokenize(command);
+    let mut words = tokens.into_iter()
+        .skip_while(|t| is_assignment(t))
+        .take_while(|t| !is_operator(t));
+    let mut path = Vec::new();
+    match words.next() {
+        Some(program) if depth > 0 => path.push(program),
+        _ => return path,
+    }
+    if takes_operands(&path[0]) {
+        return path;
+    }
+    for word in words {
+        if path.len() >= depth || !is_subcommand_word(&word) {
+            break;
+        }
+        if path.len() >= 2 && !NESTED_COMMANDS.contains(&path[0].as_str()) && !NESTED_COMMANDS.contains(&path[..2].join(" ").as_str()) {
+            break;
+        }
+        path.push(word);
+    }
+    path
+}
+
+/// Split a command line into its chained commands (`&&`, `||`, `;`, `&`), each split into
+/// pipeline stages. Every stage is a non-empty token list.
+pub fn pipelines(command: &str) -> Vec<Vec<Vec<String>>> {
+    pipeline_spans(command).into_iter()
+        .map(|chain| chain.into_iter().map(|stage| stage.into_iter().map(|(token, _)| token).collect()).collect())
+        .collect()
+}
+
+/// `pipelines`, with the byte range of every token in `command`
+pub fn pipeline_spans(command: &str) -> Vec<Vec<Vec<Spanned>>> {
+    let mut chains = vec![];
+    let mut stages = vec![];
+    let mut stage = vec![];
+    for (token, span) in tokenize_spans(command) {
+        match token.as_str() {
+            "|" => {
+                if !stage.is_empty() { stages.push(std::mem::take(&mut stage)); }
+            },
+            t if is_operator(t) => {
+                if !stage.is_empty() { stages.push(std::mem::take(&mut stage)); }
+                if !stages.is_empty() { chains.push(std::mem::take(&mut stages)); }
+            },
+            _ => stage.push((token, span)),
+        }
+    }
+    if !stage.is_empty() { stages.push(stage); }
+    if !stages.is_empty() { chains.push(stages); }
+    chains
+}
+
+/// Program name of a pipeline stage, skipping leading environment assignments
+pub fn stage_program(stage: &[String]) -> Option<&str> {
+    stage.iter().find(|t| !is_assignment(t)).map(|t| t.as_str())
+}
+
+/// Program plus its leading flags (`uniq -c`, `sort -rn`), used to label pipeline pairs
+pub fn stage_label(stage: &[String]) -> String {
+    let mut words = stage.iter().skip_while(|t| is_assignment(t));
+    let mut label = match words.next() {
+        Some(program) => program.clone(),
+        None => return String::new(),
+    };
+    for flag in words.take_while(|t| t.starts_with('-') && t.len() > 1) {
+        label.push(' ');
+        label.push_str(flag);
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_quotes_and_operators() {
+        assert_eq!(
+            tokenize(r#"git commit -m "fix: it's done" && echo 'a b'|wc"#),
+            vec!["git", "commit", "-m", "fix: it's done", "&&", "echo", "a b", "|", "wc"]
+        );
+        assert_eq!(tokenize(r"echo a\ b"), vec!["echo", "a b"]);
+        assert_eq!(tokenize("make 2>&1 | tee log"), vec!["make", "2>&1", "|", "tee", "log"]);
+        let spans: Vec<_> = tokenize_spans(r#"ls "a b"&&x"#).into_iter().map(|(_, span)| span).collect();
+        assert_eq!(spans, vec![0..2, 3..8, 8..10, 10..11]);
+    }
+
+    #[test]
+    fn test_command_path_depth() {
+        assert_eq!(command_path("kubectl get pods -n prod", 3), vec!["kubectl", "get", "pods"]);
+        assert_eq!(command_path("git commit -m 'msg'", 3), vec!["git", "commit"]);
+        assert_eq!(command_path("git commit -m 'msg'", 1), vec!["git"]);
+        assert_eq!(command_path("RUST_LOG=debug cargo test", 2), vec!["cargo", "test"]);
+        assert_eq!(command_path("cat ./x.txt | sort", 2), vec!["cat"]);
+        assert!(command_path("", 2).is_empty());
+        // File and directory operands are not subcommands
+        assert_eq!(command_path("cat foo.txt", 2), vec!["cat"]);
+        assert_eq!(command_path("cd /tmp", 2), vec!["cd"]);
+        assert_eq!(command_path("cd projects", 2), vec!["cd"]);
+        assert_eq!(command_path("vim file.rs", 2), vec!["vim"]);
+        assert_eq!(command_path("make Makefile.am", 2), vec!["make"]);
+        assert_eq!(command_path("python3 manage", 2), vec!["python3"]);
+        assert_eq!(command_path("python3.12 -m venv .venv", 2), vec!["python3.12"]);
+        assert_eq!(command_path("ssh prod-db", 2), vec!["ssh"]);
+        assert_eq!(command_path("node server", 2), vec!["node"]);
+        assert_eq!(command_path("git checkout feature-x", 3), vec!["git", "checkout"]);
+        assert_eq!(command_path("npm install lodash", 3), vec!["npm", "install"]);
+        assert_eq!(command_path("npm run build", 3), vec!["npm", "run", "build"]);
+        assert_eq!(command_path("git stash pop", 3), vec!["git", "stash", "pop"]);
+        assert_eq!(command_path("docker compose up -d", 3), vec!["docker", "compose", "up"]);
+    }
+
+    #[test]
+    fn test_pipelines_and_labels() {
+        let chains = pipelines("cat log | grep -v debug | sort | uniq -c && echo done");
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0].len(), 4);
+        assert_eq!(stage_program(&chains[0][1]), Some("grep"));
+        assert_eq!(stage_label(&chains[0][1]), "grep -v");
+        assert_eq!(stage_label(&chains[0][3]), "uniq -c");
+        assert_eq!(chains[1], vec![vec!["echo".to_string(), "done".to_string()]]);
+        assert!(pipelines("").is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+//! Typo detection: mistyped programs, missing spaces and misspelt arguments
+//! Generates correction aliases for the common ones (`gti` → `git`, `cd..` → `cd ..`)
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::aliases::{taken_names, AliasDef, AliasSuggestion};
+use crate::models::HistoryEntry;
+use crate::tokenizer::{command_path, tokenize};
+
+/// A command retyped within this many seconds counts as a correction
+const RETYPE_SECONDS: i64 = 60;
+
+/// Programs used at least this often are candidate corrections for unknown ones
+const MIN_PROGRAM_USES: usize = 3;
+
+/// What was mistyped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum TypoKind {
+    /// Misspelt program name (`gti status`)
+    Program,
+    /// Missing space after the program (`cd..`, `ls-la`)
+    Spacing,
+    /// Misspelt argument or subcommand (`git stauts`)
+    Argument,
+}
+
+/// A recurring mistake and its likely correction
+#[derive(Debug, Clone, Serialize)]
+pub struct Typo {
+    pub typo: String,
+    pub correction: String,
+    pub kind: TypoKind,
+    /// Occurrences of the mistyped program, or retypes for argument typos
+    pub count: usize,
+    /// Times the mistake was immediately followed by the corrected command
+    pub retyped: usize,
+}
+
+impl Typo {
+    /// Whether an alias named after the typo can fix it
+    pub fn aliasable(&self) -> bool {
+        self.kind != TypoKind::Argument
+            && self.typo.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'))
+    }
+}
+
+/// Optimal string alignment distance: edits, with adjacent transpositions counting once
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Edits allowed before a word stops looking like a typo of `word`
+fn max_distance(word: &str) -> usize {
+    if word.chars().count() <= 4 { 1 } else { 2 }
+}
+
+/// `cd..` → `cd ..` when the word is a known program glued to punctuation
+fn split_glued(word: &str, programs: &HashMap<String, usize>) -> Option<String> {
+    programs.iter()
+        .filter(|(p, count)| **count >= MIN_PROGRAM_USES && word.len() > p.len() && word.starts_with(p.as_str()))
+        .filter(|(p, _)| word[p.len()..].starts_with(['.', '-', '/', '~']))
+        .max_by_key(|(p, count)| (p.len(), **count))
+        .map(|(p, _)| format!("{} {}", p, &word[p.len()..]))
+}
+
+/// Find likely typos. `reserved` holds executables on `$PATH`; together with shell builtins and
+/// `defs` it decides which programs exist. Mistakes come from commands retyped within a minute
+/// with a small edit, and from unknown programs close to frequently used ones.
+pub fn find_typos(history: &[HistoryEntry], defs: &[AliasDef], reserved: &HashSet<String>) -> Vec<Typo> {
+    let known = taken_names(defs, reserved);
+    let mut programs: HashMap<String, usize> = HashMap::new();
+    let mut words: HashMap<String, usize> = HashMap::new();
+    for entry in history {
+        if let Some(program) = command_path(&entry.command, 1).pop() {
+            *programs.entry(program).or_insert(0) += 1;
+        }
+        for token in tokenize(&entry.command) {
+            *words.entry(token).or_insert(0) += 1;
+        }
+    }
+    let is_unknown = |p: &str| !known.contains(p) && !p.contains('/');
+
+    // (typo, correction, kind) → retypes
+    let mut retyped: HashMap<(String, String, TypoKind), usize> = HashMap::new();
+    for pair in history.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        if let (Some(ta), Some(tb)) = (a.timestamp, b.timestamp) {
+            if tb.signed_duration_since(ta).num_seconds() > RETYPE_SECONDS {
+                continue;
+            }
+        }
+        let (wa, wb) = (tokenize(&a.command), tokenize(&b.command));
+        if wa.is_empty() || wb.is_empty() || wa == wb {
+            continue;
+        }
+        let found = if wa.len() == wb.len() && wa[1..] == wb[1..] {
+            (is_unknown(&wa[0]) && known.contains(&wb[0]) && edit_distance(&wa[0], &wb[0]) <= max_distance(&wb[0]))
+                .then(|| (wa[0].clone(), wb[0].clone(), TypoKind::Program))
+        } else if wa.len() == 1 && wb.len() > 1 && wb.concat() == wa[0] {
+            (is_unknown(&wa[0]) && known.contains(&wb[0]))
+                .then(|| (wa[0].clone(), b.command.trim().to_string(), TypoKind::Spacing))
+        } else if wa.len() == wb.len() && wa[0] == wb[0] {
+            let diff: Vec<usize> = (1..wa.len()).filter(|&i| wa[i] != wb[i]).collect();
+            match diff.as_slice() {
+                [i] => {
+                    let (x, y) = (&wa[*i], &wb[*i]);
+                    let digits_only = x.replace(|c: char| c.is_ascii_digit(), "") == y.replace(|c: char| c.is_ascii_digit(), "");
+                    (x.len() >= 4 && y.len() >= 4 && !digits_only && edit_distance(x, y) == 1
+                        && words.get(x).copied().unwrap_or(0) < words.get(y).copied().unwrap_or(0))
+                        .then(|| (a.command.trim().to_string(), b.command.trim().to_string(), TypoKind::Argument))
+                },
+                _ => None,
+            }
+        } else {
+            None
+        };
+        if let Some(key) = found {
+            *retyped.entry(key).or_insert(0) += 1;
+        }
+    }
+
+    let mut typos: Vec<Typo> = vec![];
+    let mut seen: HashSet<String> = HashSet::new();
+    // Retyped mistakes first: the user's own correction is the best evidence
+    let mut retyped_vec: Vec<_> = retyped.into_iter().collect();
+    retyped_vec.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    for ((typo, correction, kind), times) in retyped_vec {
+        if !seen.insert(typo.clone()) {
+            continue;
+        }
+        let count = match kind {
+            TypoKind::Argument => times,
+            _ => programs.get(&typo).copied().unwrap_or(times),
+        };
+        typos.push(Typo { typo, correction, kind, count, retyped: times });
+    }
+    // Unknown programs close to a frequently used one
+    for (program, &count) in &programs {
+        if seen.contains(program) || !is_unknown(program) {
+            continue;
+        }
+        let nearest = programs.iter()
+            .filter(|(p, c)| **c >= MIN_PROGRAM_USES && **c > count && known.contains(p.as_str()))
+            .map(|(p, c)| (edit_distance(program, p), p, c))
+            .filter(|(d, p, _)| *d <= max_distance(p))
+            .min_by(|a, b| a.0.cmp(&b.0).then_with(|| b.2.cmp(a.2)).then_with(|| a.1.cmp(b.1)));
+        let found = match nearest {
+            Some((_, p, _)) => Some((p.clone(), TypoKind::Program)),
+            None => split_glued(program, &programs).map(|c| (c, TypoKind::Spacing)),
+        };
+        if let Some((correction, kind)) = found {
+            seen.insert(program.clone());
+            typos.push(Typo { typo: program.clone(), correction, kind, count, retyped: 0 });
+        }
+    }
+    typos.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.typo.cmp(&b.typo)));
+    typos
+}
+
+/// Correction aliases for typos that an alias can fix, ready for `format_alias`/`write_alias_file`
+pub fn correction_aliases(typos: &[Typo]) -> Vec<AliasSuggestion> {
+    typos.iter()
+        .filter(|t| t.aliasable())
+        .map(|t| AliasSuggestion { name: t.typo.clone(), command: t.correction.clone(), count: t.count })
+        .collect()
+}
+
+/// Print the most common typos
+pub fn print_typos(typos: &[Typo]) {
+    println!("\nLikely typos ({}):", typos.len());
+    if typos.is_empty() {
+        println!("No typos found.");
+        return;
+    }
+    println!("{:<24} {:<24} {:>6} {:>8}  Kind", "Typo", "Correction", "Count", "Retyped");
+    for t in typos.iter().take(20) {
+        println!("{:<24} {:<24} {:>6} {:>8}  {:?}", t.typo, t.correction, t.count, t.retyped, t.kind);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    fn at(secs: i64, command: &str) -> HistoryEntry {
+        HistoryEntry { timestamp: Local.timestamp_opt(1_700_000_000 + secs, 0).single(), command: command.to_string(), session_id: None, ..Default::default() }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("gti", "git"), 1);
+        assert_eq!(edit_distance("dokcer", "docker"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "ls"), 2);
+    }
+
+    #[test]
+    fn test_find_typos() {
+        let history = vec![
+            at(0, "gti status"), at(5, "git status"),
+            at(100, "git stauts"), at(104, "git status"),
+            at(200, "cd.."), at(202, "cd .."),
+            at(300, "dokcer ps"), at(400, "docker ps"), at(500, "docker ps"), at(600, "docker images"),
+            at(700, "gti push"), at(1000, "git push"),
+            at(1100, "kill 1234"), at(1105, "kill 1235"),
+        ];
+        let reserved: HashSet<String> = ["git", "docker", "kill"].iter().map(|s| s.to_string()).collect();
+        let typos = find_typos(&history, &[], &reserved);
+        let find = |t: &str| typos.iter().find(|x| x.typo == t);
+        let gti = find("gti").expect("gti");
+        assert_eq!((gti.correction.as_str(), gti.kind, gti.count, gti.retyped), ("git", TypoKind::Program, 2, 1));
+        assert_eq!(find("cd..").map(|t| (t.correction.as_str(), t.kind)), Some(("cd ..", TypoKind::Spacing)));
+        assert_eq!(find("dokcer").map(|t| (t.correction.as_str(), t.retyped)), Some(("docker", 0)));
+        assert_eq!(find("git stauts").map(|t| t.kind), Some(TypoKind::Argument));
+        assert!(find("kill 1234").is_none());
+        let aliases = correction_aliases(&typos);
+        assert_eq!(aliases.len(), 3);
+        assert!(aliases.iter().all(|a| a.name != "git stauts"));
+    }
+}
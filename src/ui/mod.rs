@@ -1,10 +1,21 @@
 //! TUI rendering module using ratatui + crossterm
 
-use crate::cli::CliArgs;
+use crate::aliases::{alias_suggestions, path_executables, AliasSuggestion};
+use crate::analyzer::{command_tree, group_sessions, pipeline_stats, CommandNode, PipelineStats};
+use crate::cli::{CliArgs, StatusFilter};
 use crate::models::HistoryEntry;
+use crate::cwd::{directory_stats, display_dir, DirStats};
+use crate::dangerous::{scan as scan_dangerous, DangerousCommand, Severity};
+use crate::durations::{duration_report, format_duration, DurationReport};
+use crate::hosts::{host_stats, local_hostname, HostStats};
+use crate::secrets::{scan_secrets, Confidence, SecretReport};
+use crate::projects::{project_stats, ProjectResolver, ProjectStats};
+use crate::retries::{retry_report, RetryReport};
+use crate::workflows::{mine_workflows, workflow_script, Workflow};
 use anyhow::Result;
 use crossterm::{event, execute, terminal};
 use ratatui::{prelude::*, widgets::*};
+use std::collections::HashSet;
 use std::fs::OpenOptions;
 use std::io::{self, Write as IoWrite};
 use std::sync::{Arc, Mutex};
@@ -26,6 +37,40 @@ enum Tab {
     Host,
     TimeOfDay,
     Heatmap,
+    Pipelines,
+    Workflows,
+    Retries,
+    Durations,
+    Secrets,
+}
+
+impl Tab {
+    /// Tabs in display order; must match `TAB_ICONS`
+    const ALL: [Tab; 15] = [
+        Tab::Summary,
+        Tab::PerCommand,
+        Tab::Sessions,
+        Tab::Search,
+        Tab::Aliases,
+        Tab::Dangerous,
+        Tab::Directory,
+        Tab::Host,
+        Tab::TimeOfDay,
+        Tab::Heatmap,
+        Tab::Pipelines,
+        Tab::Workflows,
+        Tab::Retries,
+        Tab::Durations,
+        Tab::Secrets,
+    ];
+
+    fn next(self) -> Tab {
+        Tab::ALL[(self as usize + 1) % Tab::ALL.len()]
+    }
+
+    fn prev(self) -> Tab {
+        Tab::ALL[(self as usize + Tab::ALL.len() - 1) % Tab::ALL.len()]
+    }
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -42,7 +87,7 @@ enum Theme {
     Colorblind,
 }
 
-const TAB_ICONS: [&str; 10] = [
+const TAB_ICONS: [&str; 15] = [
     "󰄮 Summary",      // Dashboard
     " Commands",     // Terminal
     " Sessions",     // Calendar
@@ -53,6 +98,11 @@ const TAB_ICONS: [&str; 10] = [
     "󰒋 Host",         // Server
     "󰥔 TimeOfDay",    // Clock
     " Heatmap",      // Chart
+    "󰈲 Pipelines",    // Funnel
+    "󰑮 Workflows",    // Route
+    "󰑓 Retries",      // Reload
+    "󱎫 Durations",    // Timer
+    "󰌆 Secrets",      // Key
 ];
 
 macro_rules! log_error {
@@ -65,14 +115,60 @@ macro_rules! log_error {
     }};
 }
 
-pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
+/// Flatten the expanded part of a command tree into `(level, path, node)` rows, top 10 per level
+fn visible_tree_rows<'a>(nodes: &'a [CommandNode], expanded: &HashSet<Vec<String>>, prefix: &[String], level: usize, rows: &mut Vec<(usize, Vec<String>, &'a CommandNode)>) {
+    for node in nodes.iter().take(10) {
+        let mut path = prefix.to_vec();
+        path.push(node.name.clone());
+        let is_expanded = expanded.contains(&path);
+        rows.push((level, path.clone(), node));
+        if is_expanded {
+            visible_tree_rows(&node.children, expanded, &path, level + 1, rows);
+        }
+    }
+}
+
+/// Save a workflow as `heist_workflow_N.sh` in the current directory; an existing file is
+/// never overwritten
+fn save_workflow(workflow: &Workflow, number: usize) -> String {
+    let path = format!("heist_workflow_{}.sh", number);
+    let written = OpenOptions::new().write(true).create_new(true).open(&path)
+        .and_then(|mut file| file.write_all(workflow_script(workflow).as_bytes()));
+    match written {
+        Ok(()) => format!("Saved workflow to {}", path),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => format!("{} already exists; not overwritten", path),
+        Err(e) => format!("Failed to save {}: {}", path, e),
+    }
+}
+
+/// Split a tab into left and right halves; narrow terminals get the whole area on the left
+/// and an empty right pane
+fn split_columns(area: Rect) -> (Rect, Rect) {
+    if area.width > 60 {
+        (Rect { x: area.x, y: area.y, width: area.width/2, height: area.height },
+         Rect { x: area.x+area.width/2, y: area.y, width: area.width-area.width/2, height: area.height })
+    } else {
+        (area, Rect { x: 0, y: 0, width: 0, height: 0 })
+    }
+}
+
+pub fn run_tui(history: &Vec<HistoryEntry>, cli_args: &CliArgs) -> Result<()> {
     if !atty::is(Stream::Stdout) {
         eprintln!("[heist error] TUI requires a real terminal. Run in a supported terminal emulator.");
         return Ok(());
     }
+    // `--redact` masks everything the TUI shows; the secret scan reads the raw history
+    let raw_history = history;
+    let redacted;
+    let history: &Vec<HistoryEntry> = if cli_args.redact {
+        redacted = crate::redact::redact_history(raw_history, cli_args)?;
+        &redacted
+    } else {
+        raw_history
+    };
     // Replace get_history_path and load_history_from_file with correct parser logic
     let shell = crate::parser::detect_shell();
-    let args = _args.clone();
+    let args = cli_args.clone();
     let history_data = Arc::new(Mutex::new(history.clone()));
     let history_data_clone = Arc::clone(&history_data);
     thread::spawn(move || {
@@ -83,6 +179,9 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
             };
             let live_entries = crate::parser::parse_heist_live_history();
             new_history.extend(live_entries);
+            if args.redact {
+                new_history = crate::redact::redact_history(&new_history, &args).unwrap_or_default();
+            }
             let mut data = history_data_clone.lock().unwrap();
             *data = new_history;
             std::thread::sleep(Duration::from_secs(1));
@@ -150,57 +249,48 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
     let mut search_mode = false;
     let mut search_query = String::new();
     let mut search_results: Vec<HistoryEntry> = vec![];
+    // Exit-status filter for search results, cycled with Tab while searching
+    let mut search_status: Option<StatusFilter> = cli_args.status;
     let mut search_selected: usize = 0;
 
-    // Cache summary data to avoid flicker
-    let freq_vec: Vec<(String, usize)> = {
-        use std::collections::HashMap;
-        let mut freq: HashMap<String, usize> = HashMap::new();
-        for entry in history {
-            let cmd = entry.command.split_whitespace().next().unwrap_or("").to_string();
-            *freq.entry(cmd).or_insert(0) += 1;
-        }
-        let mut freq_vec: Vec<_> = freq.into_iter().collect();
-        freq_vec.sort_by(|a, b| b.1.cmp(&a.1));
-        freq_vec
-    };
+    // Cache summary data to avoid flicker; rows expand into subcommands on Enter
+    let summary_tree: Vec<CommandNode> = command_tree(history, cli_args.depth.unwrap_or(3).max(1));
+    let mut summary_expanded: HashSet<Vec<String>> = HashSet::new();
+    let mut summary_selected: usize = 0;
     // Cache alias suggestions to avoid flicker
-    let alias_vec: Vec<(String, usize)> = {
-        use std::collections::HashMap;
-        let mut freq: HashMap<String, usize> = HashMap::new();
-        for entry in history {
-            let cmd = entry.command.trim().to_string();
-            if cmd.len() > 15 {
-                *freq.entry(cmd).or_insert(0) += 1;
-            }
-        }
-        let mut alias_vec: Vec<_> = freq.into_iter().collect();
-        alias_vec.sort_by(|a, b| b.1.cmp(&a.1));
-        alias_vec
+    let alias_vec: Vec<AliasSuggestion> = alias_suggestions(history, crate::aliases::definitions(cli_args), &path_executables());
+    let dangerous_vec: Vec<DangerousCommand> = scan_dangerous(history, crate::dangerous::rules(cli_args));
+    // Cache per-directory stats (reconstructed working directories) to avoid flicker
+    let home = crate::cwd::home_dir_string();
+    let dir_vec: Vec<DirStats> = directory_stats(history, &home);
+    let host_vec: Vec<HostStats> = host_stats(history, &local_hostname());
+    let project_vec: Vec<ProjectStats> = project_stats(history, &home, &mut ProjectResolver::new(&home));
+    // Cache pipeline analytics to avoid flicker
+    let pipeline_cache: PipelineStats = pipeline_stats(history);
+    let retry_cache: RetryReport = retry_report(history);
+    let duration_cache: DurationReport = duration_report(history);
+    let secret_cache: SecretReport = if cli_args.redact {
+        crate::redact::Redactor::from_args(cli_args, raw_history)?.redact_secret_report(&scan_secrets(raw_history))
+    } else {
+        scan_secrets(raw_history)
     };
-    // Cache per-directory stats to avoid flicker
-    let dir_vec: Vec<(String, usize)> = {
-        use std::collections::HashMap;
-        let mut dir_counts: HashMap<String, usize> = HashMap::new();
-        let mut last_dir = String::from("~");
-        for entry in history.iter() {
-            if entry.command.starts_with("cd ") {
-                let dir = entry.command[3..].trim().to_string();
-                last_dir = dir.clone();
-                *dir_counts.entry(dir).or_insert(0) += 1;
-            } else {
-                *dir_counts.entry(last_dir.clone()).or_insert(0) += 1;
-            }
-        }
-        let mut dir_vec: Vec<_> = dir_counts.into_iter().collect();
-        dir_vec.sort_by(|a, b| b.1.cmp(&a.1));
-        dir_vec
+    // Cache mined workflows; Enter saves the selected one as a script
+    let workflow_cache: Vec<Workflow> = {
+        let refs: Vec<&HistoryEntry> = history.iter().collect();
+        mine_workflows(&group_sessions(&refs, 10), cli_args.min_support, cli_args.min_confidence)
     };
-    let max_count = freq_vec.first().map(|x| x.1).unwrap_or(1);
+    let mut workflow_selected: usize = 0;
+    let mut status_message: Option<String> = None;
+    let max_count = summary_tree.first().map(|n| n.count).unwrap_or(1);
     let total_cmds = history.len();
 
     while running {
         let history = history_data.lock().unwrap();
+        let mut summary_rows = vec![];
+        visible_tree_rows(&summary_tree, &summary_expanded, &[], 0, &mut summary_rows);
+        if summary_selected >= summary_rows.len() {
+            summary_selected = summary_rows.len().saturating_sub(1);
+        }
         if let Err(e) = terminal.draw(|f| {
             let size = f.area(); // .size() is deprecated
             let chunks = Layout::default()
@@ -223,21 +313,25 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                     // Modern summary: Table with bar visualization
                     let header = Row::new(vec!["#", "Command", "Count", "Usage"])
                         .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
-                    let rows: Vec<Row> = freq_vec.iter().take(10).enumerate().map(|(i, (cmd, count))| {
-                        let bar_len = ((*count as f64 / max_count as f64) * 20.0).round() as usize;
+                    let mut rank = 0;
+                    let rows: Vec<Row> = summary_rows.iter().map(|(level, path, node)| {
+                        let bar_len = ((node.count as f64 / max_count as f64) * 20.0).round() as usize;
                         let bar = "█".repeat(bar_len);
+                        let marker = if node.children.is_empty() { " " } else if summary_expanded.contains(path) { "▾" } else { "▸" };
+                        let number = if *level == 0 { rank += 1; format!("{:>2}", rank) } else { String::new() };
+                        let color = if *level == 0 { Color::Green } else { Color::Cyan };
                         Row::new(vec![
-                            format!("{:>2}", i+1),
-                            format!("{:<15}", cmd),
-                            format!("{:>4}", count),
+                            number,
+                            format!("{}{} {}", "  ".repeat(*level), marker, node.name),
+                            format!("{:>4}", node.count),
                             bar,
-                        ]).style(Style::default().fg(Color::Green))
+                        ]).style(Style::default().fg(color))
                     }).collect();
                     let table = Table::new(
                         rows,
                         [
                             Constraint::Length(3),
-                            Constraint::Length(16),
+                            Constraint::Length(28),
                             Constraint::Length(6),
                             Constraint::Min(10),
                         ]
@@ -246,7 +340,9 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                         .block(Block::default().title("Top Commands ").borders(Borders::ALL).title_alignment(Alignment::Center))
                         .column_spacing(1)
                         .row_highlight_style(Style::default().bg(Color::Blue).fg(Color::White).add_modifier(Modifier::BOLD)); // highlight_style -> row_highlight_style
-                    f.render_widget(table, chunks[1]);
+                    let mut state = TableState::default();
+                    state.select(Some(summary_selected));
+                    f.render_stateful_widget(table, chunks[1], &mut state);
                     // Subtitle with total commands
                     let subtitle = Paragraph::new(format!("Total commands: {}", total_cmds))
                         .style(Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC));
@@ -287,12 +383,7 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                 },
                 Tab::Sessions => {
                     let area = chunks[1];
-                    let (left, right) = if area.width > 60 {
-                        (Rect { x: area.x, y: area.y, width: area.width/2, height: area.height },
-                         Rect { x: area.x+area.width/2, y: area.y, width: area.width-area.width/2, height: area.height })
-                    } else {
-                        (area, Rect { x: 0, y: 0, width: 0, height: 0 })
-                    };
+                    let (left, right) = split_columns(area);
                     // Session list
                     let session_items: Vec<ListItem> = sessions.iter().enumerate().map(|(i, (start, end, cmds))| {
                         ListItem::new(format!("Session {:>2}: {} - {} ({} cmds)", i+1, start.format("%Y-%m-%d %H:%M"), end.format("%H:%M"), cmds.len()))
@@ -319,8 +410,13 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                 },
                 Tab::Search => {
                     let area = chunks[1];
+                    let status_label = match search_status {
+                        Some(StatusFilter::Failed) => "failed",
+                        Some(StatusFilter::Succeeded) => "succeeded",
+                        None => "all",
+                    };
                     let input = Paragraph::new(format!("Search: {}", search_query))
-                        .block(Block::default().title("Search").borders(Borders::ALL))
+                        .block(Block::default().title(format!("Search [status: {}, Tab to cycle]", status_label)).borders(Borders::ALL))
                         .style(Style::default().fg(Color::Yellow));
                     f.render_widget(input, Rect { x: area.x, y: area.y, width: area.width, height: 3 });
                     let results_area = Rect { x: area.x, y: area.y+3, width: area.width, height: area.height.saturating_sub(3) };
@@ -333,7 +429,10 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                                 styled.replace_range(idx..idx+search_query.len(), &format!("{{{}}}", &search_query));
                             }
                         }
-                        ListItem::new(styled)
+                        match e.exit_code {
+                            Some(code) if code != 0 => ListItem::new(format!("[{}] {}", code, styled)).style(Style::default().fg(Color::Red)),
+                            _ => ListItem::new(styled),
+                        }
                     }).collect();
                     let mut list = List::new(items)
                         .block(Block::default().title("Results").borders(Borders::ALL))
@@ -344,14 +443,14 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                     f.render_stateful_widget(list, results_area, &mut state);
                 },
                 Tab::Aliases => {
-                    let rows: Vec<Row> = alias_vec.iter().take(10).enumerate().map(|(i, (cmd, count))| {
+                    let rows: Vec<Row> = alias_vec.iter().map(|s| {
                         Row::new(vec![
-                            format!(" a{}", i+1),
-                            format!("{}", cmd),
-                            format!("{}", count),
+                            format!(" {}", s.name),
+                            s.command.clone(),
+                            s.count.to_string(),
                         ]).style(Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD))
                     }).collect();
-                    let table = Table::new(rows, [Constraint::Length(6), Constraint::Min(30), Constraint::Length(6)])
+                    let table = Table::new(rows, [Constraint::Length(9), Constraint::Min(30), Constraint::Length(6)])
                         .header(Row::new(vec!["Alias", "Command", "Count"]).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD | Modifier::UNDERLINED)))
                         .block(Block::default().title("Alias Suggestions ").borders(Borders::ALL).title_alignment(Alignment::Center))
                         .column_spacing(1)
@@ -359,14 +458,18 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                     f.render_widget(table, chunks[1]);
                 },
                 Tab::Dangerous => {
-                    let patterns = ["rm -rf", "rm -r /", "dd if=", "mkfs", ":(){ :|:& };:", "shutdown", "reboot", "curl | sh", "wget | sh", "chmod 777 /", "chown root", "> /dev/sda", "/dev/sda", ":(){ :|: & };:", "rm -rf --no-preserve-root", "poweroff", "halt", "init 0", "mkfs.ext", "dd of=/dev/", "mv /", "cp /dev/null", "yes | rm", "yes | dd", "yes | mkfs"];
                     let mut items: Vec<ListItem> = vec![];
-                    for entry in history.iter() {
-                        for pat in &patterns {
-                            if entry.command.contains(pat) {
-                                items.push(ListItem::new(format!("⚠️  {} (pattern: '{}')", entry.command, pat)).style(Style::default().fg(Color::Red)));
-                                break;
-                            }
+                    for d in &dangerous_vec {
+                        let style = match d.severity {
+                            Severity::Critical => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                            Severity::High => Style::default().fg(Color::Red),
+                            Severity::Medium => Style::default().fg(Color::Yellow),
+                            Severity::Low => Style::default().fg(Color::Gray),
+                        };
+                        let times = if d.count > 1 { format!(" (×{})", d.count) } else { String::new() };
+                        items.push(ListItem::new(format!("⚠️  [{}] {}{}", d.severity.label(), d.command, times)).style(style));
+                        if let Some(m) = d.matches.first() {
+                            items.push(ListItem::new(format!("    ↳ {}: {} Fix: {}", m.category, m.reason, m.remediation)));
                         }
                     }
                     if items.is_empty() {
@@ -376,23 +479,34 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                     f.render_widget(list, chunks[1]);
                 },
                 Tab::Directory => {
-                    let rows: Vec<Row> = dir_vec.iter().take(15).map(|(dir, count)| Row::new(vec![dir.clone(), count.to_string()])).collect();
-                    let table = Table::new(rows, [Constraint::Min(30), Constraint::Length(6)])
-                        .header(Row::new(vec!["Directory", "Count"]).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+                    let area = chunks[1];
+                    let (left, right) = split_columns(area);
+                    let header_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+                    let rows: Vec<Row> = dir_vec.iter().take(15).map(|s| {
+                        Row::new(vec![display_dir(&s.dir, &home), s.commands.to_string(), s.subtree.to_string()])
+                    }).collect();
+                    let table = Table::new(rows, [Constraint::Min(30), Constraint::Length(8), Constraint::Length(8)])
+                        .header(Row::new(vec!["Directory", "Commands", "Subtree"]).style(header_style))
                         .block(Block::default().title("Per-Directory Stats ").borders(Borders::ALL).title_alignment(Alignment::Center));
-                    f.render_widget(table, chunks[1]);
+                    f.render_widget(table, left);
+                    if right.width > 0 {
+                        let rows: Vec<Row> = project_vec.iter().take(15).map(|p| {
+                            Row::new(vec![p.name.clone(), format_duration(p.active_ms), format!("{}/{}", p.builds, p.tests), p.active_days.to_string()])
+                                .style(Style::default().fg(Color::Green))
+                        }).collect();
+                        let table = Table::new(rows, [Constraint::Min(16), Constraint::Length(9), Constraint::Length(11), Constraint::Length(4)])
+                            .header(Row::new(vec!["Project", "Active", "Build/Test", "Days"]).style(header_style))
+                            .block(Block::default().title("Projects").borders(Borders::ALL).title_alignment(Alignment::Center));
+                        f.render_widget(table, right);
+                    }
                 },
                 Tab::Host => {
-                    use std::collections::HashMap;
-                    use std::env;
-                    let mut host_counts: HashMap<String, usize> = HashMap::new();
-                    let hostname = env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
-                    for _ in history.iter() {
-                        *host_counts.entry(hostname.clone()).or_insert(0) += 1;
-                    }
-                    let rows: Vec<Row> = host_counts.iter().map(|(host, count)| Row::new(vec![host.clone(), count.to_string()])).collect();
-                    let table = Table::new(rows, [Constraint::Min(20), Constraint::Length(6)])
-                        .header(Row::new(vec!["Host", "Count"]).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+                    let join = |tools: &[(String, usize)]| tools.iter().map(|(t, n)| format!("{} ({})", t, n)).collect::<Vec<_>>().join(", ");
+                    let rows: Vec<Row> = host_vec.iter().map(|h| {
+                        Row::new(vec![h.host.clone(), h.commands.to_string(), join(&h.top_commands), join(&h.unique_commands)])
+                    }).collect();
+                    let table = Table::new(rows, [Constraint::Length(20), Constraint::Length(8), Constraint::Percentage(45), Constraint::Percentage(35)])
+                        .header(Row::new(vec!["Host", "Count", "Top Commands", "Only Here"]).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
                         .block(Block::default().title("Per-Host Stats 󰒋").borders(Borders::ALL).title_alignment(Alignment::Center));
                     f.render_widget(table, chunks[1]);
                 },
@@ -447,6 +561,124 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                         .block(Block::default().title("Weekly Heatmap ").borders(Borders::ALL).title_alignment(Alignment::Center));
                     f.render_widget(table, chunks[1]);
                 },
+                Tab::Pipelines => {
+                    let area = chunks[1];
+                    let (left, right) = split_columns(area);
+                    let header_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+                    let mut rows: Vec<Row> = pipeline_cache.top_pairs.iter().map(|(pair, count)| {
+                        Row::new(vec![pair.clone(), count.to_string()]).style(Style::default().fg(Color::Green))
+                    }).collect();
+                    rows.push(Row::new(vec![String::new(), String::new()]));
+                    rows.push(Row::new(vec!["Stage program".to_string(), "Count".to_string()]).style(header_style));
+                    rows.extend(pipeline_cache.top_stages.iter().map(|(program, count)| {
+                        Row::new(vec![program.clone(), count.to_string()]).style(Style::default().fg(Color::Cyan))
+                    }));
+                    let title = format!("Pipelines: {} (avg {:.1} stages) ", pipeline_cache.pipelines, pipeline_cache.average_length);
+                    let table = Table::new(rows, [Constraint::Min(20), Constraint::Length(6)])
+                        .header(Row::new(vec!["Pair", "Count"]).style(header_style))
+                        .block(Block::default().title(title).borders(Borders::ALL).title_alignment(Alignment::Center));
+                    f.render_widget(table, left);
+                    if right.width > 0 {
+                        let items: Vec<ListItem> = pipeline_cache.longest.iter()
+                            .map(|(len, cmd)| ListItem::new(format!("[{}] {}", len, cmd)))
+                            .collect();
+                        let list = List::new(items).block(Block::default().title("Longest Pipelines").borders(Borders::ALL));
+                        f.render_widget(list, right);
+                    }
+                },
+                Tab::Retries => {
+                    let area = chunks[1];
+                    let (left, right) = split_columns(area);
+                    let header_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+                    let rows: Vec<Row> = retry_cache.tools.iter().map(|t| {
+                        Row::new(vec![t.tool.clone(), t.retries.to_string(), format!("{:.0}%", t.retry_rate() * 100.0), format!("{}/{}/{}", t.reruns, t.sudo, t.flag_changes)])
+                            .style(Style::default().fg(if t.retry_rate() > 0.25 { Color::Red } else { Color::Green }))
+                    }).collect();
+                    let title = format!("Retries: {} inferred ", retry_cache.retries);
+                    let table = Table::new(rows, [Constraint::Min(16), Constraint::Length(7), Constraint::Length(5), Constraint::Length(10)])
+                        .header(Row::new(vec!["Tool", "Retries", "Rate", "Run/Sudo/Flag"]).style(header_style))
+                        .block(Block::default().title(title).borders(Borders::ALL).title_alignment(Alignment::Center));
+                    f.render_widget(table, left);
+                    if right.width > 0 {
+                        let items: Vec<ListItem> = retry_cache.corrections.iter()
+                            .map(|c| ListItem::new(format!("{:>3}x {}: {}", c.count, c.tool, c.change)))
+                            .collect();
+                        let list = List::new(items).block(Block::default().title("Recurring Corrections").borders(Borders::ALL));
+                        f.render_widget(list, right);
+                    }
+                },
+                Tab::Durations => {
+                    let area = chunks[1];
+                    let (left, right) = split_columns(area);
+                    let header_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+                    let rows: Vec<Row> = duration_cache.tools.iter().map(|t| {
+                        Row::new(vec![t.tool.clone(), format_duration(t.total_ms), format_duration(t.mean_ms), format!("{:.2}", t.hours_per_week)])
+                            .style(Style::default().fg(Color::Green))
+                    }).collect();
+                    let title = format!("Time per Tool: {} over {} runs ", format_duration(duration_cache.total_ms), duration_cache.timed);
+                    let table = Table::new(rows, [Constraint::Min(16), Constraint::Length(9), Constraint::Length(9), Constraint::Length(7)])
+                        .header(Row::new(vec!["Tool", "Total", "Mean", "h/week"]).style(header_style))
+                        .block(Block::default().title(title).borders(Borders::ALL).title_alignment(Alignment::Center));
+                    f.render_widget(table, left);
+                    if right.width > 0 {
+                        let mut items: Vec<ListItem> = duration_cache.slowest.iter()
+                            .map(|c| ListItem::new(format!("{:>9} {}", format_duration(c.duration_ms), c.command)))
+                            .collect();
+                        items.push(ListItem::new("Outliers (vs usual):").style(header_style));
+                        items.extend(duration_cache.outliers.iter().map(|o| {
+                            ListItem::new(format!("{:>9} vs {} {}", format_duration(o.duration_ms), format_duration(o.median_ms), o.command))
+                                .style(Style::default().fg(Color::Red))
+                        }));
+                        items.push(ListItem::new("Weekly total:").style(header_style));
+                        items.extend(duration_cache.weekly.iter().rev().take(4).map(|w| {
+                            ListItem::new(format!("{} {:>9} ({} runs)", w.week, format_duration(w.total_ms), w.runs))
+                        }));
+                        let list = List::new(items).block(Block::default().title("Slowest Commands").borders(Borders::ALL));
+                        f.render_widget(list, right);
+                    }
+                },
+                Tab::Secrets => {
+                    let mut items: Vec<ListItem> = secret_cache.findings.iter().map(|f| {
+                        let style = match f.confidence {
+                            Confidence::High => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                            Confidence::Medium => Style::default().fg(Color::Yellow),
+                            Confidence::Low => Style::default().fg(Color::Gray),
+                        };
+                        let when = f.timestamp.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "-".to_string());
+                        ListItem::new(format!("#{:<5} {} {:<20} {}", f.entry, when, f.kind.label(), f.command)).style(style)
+                    }).collect();
+                    if items.is_empty() {
+                        items.push(ListItem::new("No secrets found.").style(Style::default().fg(Color::Green)));
+                    }
+                    let title = format!("Secrets: {} in {} commands (values masked) ", secret_cache.findings.len(), secret_cache.commands_with_secrets);
+                    let list = List::new(items).block(Block::default().title(title).borders(Borders::ALL));
+                    f.render_widget(list, chunks[1]);
+                },
+                Tab::Workflows => {
+                    let area = chunks[1];
+                    let (left, right) = split_columns(area);
+                    let mut items: Vec<ListItem> = workflow_cache.iter().enumerate().map(|(i, w)| {
+                        ListItem::new(format!("{:>2}. [{}x {:.0}%] {}", i+1, w.support, w.confidence * 100.0, w.steps.join(" → ")))
+                    }).collect();
+                    if items.is_empty() {
+                        items.push(ListItem::new("No frequent workflows found.").style(Style::default().fg(Color::Gray)));
+                    }
+                    let list = List::new(items)
+                        .block(Block::default().title("Workflows (sessions, confidence)").borders(Borders::ALL))
+                        .highlight_symbol("→ ")
+                        .highlight_style(Style::default().bg(Color::Blue).fg(Color::White).add_modifier(Modifier::BOLD));
+                    let mut state = ListState::default();
+                    state.select(Some(workflow_selected));
+                    f.render_stateful_widget(list, left, &mut state);
+                    if right.width > 0 {
+                        if let Some(w) = workflow_cache.get(workflow_selected) {
+                            let preview = Paragraph::new(workflow_script(w))
+                                .style(Style::default().fg(Color::Green))
+                                .block(Block::default().title("Script [Enter] Save").borders(Borders::ALL));
+                            f.render_widget(preview, right);
+                        }
+                    }
+                },
             }
             // Show key mode in help bar
             let mode_str = match key_mode {
@@ -461,7 +693,13 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                 Theme::Colorblind => "Colorblind",
             };
             let help_string = format!("{} | Mode: {} | Theme: {}", help_text, mode_str, theme_str);
-            let help: &str = if search_mode { "Type to search, [Esc] to exit search, [Enter] to select" } else { &help_string };
+            let help: &str = if search_mode {
+                "Type to search, [Esc] to exit search, [Enter] to select"
+            } else if let Some(ref msg) = status_message {
+                msg
+            } else {
+                &help_string
+            };
             // Render help bar
             let help_bar = Paragraph::new(help);
             f.render_widget(help_bar, chunks[2]);
@@ -472,6 +710,8 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
         if event::poll(Duration::from_millis(100))? {
             match event::read()? {
                 event::Event::Key(key) => {
+                    // A saved-workflow message stays until the next key press
+                    status_message = None;
                     if key.code == event::KeyCode::F(2) {
                         key_mode = match key_mode {
                             KeyMode::Default => KeyMode::Vim,
@@ -494,6 +734,13 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                                 event::KeyCode::Char('j') => { if search_selected + 1 < search_results.len() { search_selected += 1; } },
                                 event::KeyCode::Char('k') => { if search_selected > 0 { search_selected -= 1; } },
                                 event::KeyCode::Esc => { search_mode = false; search_query.clear(); search_results.clear(); },
+                                event::KeyCode::Tab => {
+                                    search_status = match search_status {
+                                        None => Some(StatusFilter::Failed),
+                                        Some(StatusFilter::Failed) => Some(StatusFilter::Succeeded),
+                                        Some(StatusFilter::Succeeded) => None,
+                                    };
+                                },
                                 event::KeyCode::Char(c) => { search_query.push(c); },
                                 event::KeyCode::Backspace => { search_query.pop(); },
                                 _ => {}
@@ -502,12 +749,26 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                                 event::KeyCode::Char('n') if key.modifiers.contains(event::KeyModifiers::CONTROL) => { if search_selected + 1 < search_results.len() { search_selected += 1; } },
                                 event::KeyCode::Char('p') if key.modifiers.contains(event::KeyModifiers::CONTROL) => { if search_selected > 0 { search_selected -= 1; } },
                                 event::KeyCode::Esc => { search_mode = false; search_query.clear(); search_results.clear(); },
+                                event::KeyCode::Tab => {
+                                    search_status = match search_status {
+                                        None => Some(StatusFilter::Failed),
+                                        Some(StatusFilter::Failed) => Some(StatusFilter::Succeeded),
+                                        Some(StatusFilter::Succeeded) => None,
+                                    };
+                                },
                                 event::KeyCode::Char(c) => { search_query.push(c); },
                                 event::KeyCode::Backspace => { search_query.pop(); },
                                 _ => {}
                             },
                             _ => match key.code {
                                 event::KeyCode::Esc => { search_mode = false; search_query.clear(); search_results.clear(); },
+                                event::KeyCode::Tab => {
+                                    search_status = match search_status {
+                                        None => Some(StatusFilter::Failed),
+                                        Some(StatusFilter::Failed) => Some(StatusFilter::Succeeded),
+                                        Some(StatusFilter::Succeeded) => None,
+                                    };
+                                },
                                 event::KeyCode::Char(c) => { search_query.push(c); },
                                 event::KeyCode::Backspace => { search_query.pop(); },
                                 event::KeyCode::Down => { if search_selected + 1 < search_results.len() { search_selected += 1; } },
@@ -516,51 +777,50 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                             }
                         }
                         // Update search results
+                        let status_ok = |e: &&HistoryEntry| search_status.is_none_or(|status| status.matches(e));
                         let search_vec: Vec<HistoryEntry> = if !search_query.is_empty() {
                             if let Ok(re) = Regex::new(&search_query) {
-                                history.iter().filter(|e| re.is_match(&e.command)).cloned().collect()
+                                history.iter().filter(|e| re.is_match(&e.command)).filter(status_ok).cloned().collect()
                             } else {
-                                history.iter().filter(|e| e.command.contains(&search_query)).cloned().collect()
+                                history.iter().filter(|e| e.command.contains(&search_query)).filter(status_ok).cloned().collect()
                             }
                         } else { vec![] };
                         search_results = search_vec;
                         if search_selected >= search_results.len() { search_selected = 0; }
                         continue;
                     }
+                    if key.code == event::KeyCode::Enter {
+                        if tab == Tab::Workflows {
+                            if let Some(w) = workflow_cache.get(workflow_selected) {
+                                status_message = Some(save_workflow(w, workflow_selected + 1));
+                            }
+                        }
+                        if tab == Tab::Summary {
+                            if let Some((_, path, node)) = summary_rows.get(summary_selected) {
+                                if !node.children.is_empty() && !summary_expanded.remove(path) {
+                                    summary_expanded.insert(path.clone());
+                                }
+                            }
+                        }
+                        if tab == Tab::Sessions && !sessions.is_empty() && !sessions[session_selected].2.is_empty() {
+                            session_cmd_selected = (session_cmd_selected + 1) % sessions[session_selected].2.len();
+                        }
+                        continue;
+                    }
                     match key_mode {
                         KeyMode::Vim => match key.code {
                             event::KeyCode::Char('h') => {
-                                tab = match tab {
-                                    Tab::Summary => Tab::Search,
-                                    Tab::PerCommand => Tab::Summary,
-                                    Tab::Sessions => Tab::PerCommand,
-                                    Tab::Search => Tab::Sessions,
-                                    Tab::Aliases => Tab::Summary,
-                                    Tab::Dangerous => Tab::Aliases,
-                                    Tab::Directory => Tab::Dangerous,
-                                    Tab::Host => Tab::Directory,
-                                    Tab::TimeOfDay => Tab::Host,
-                                    Tab::Heatmap => Tab::TimeOfDay,
-                                };
-                                selected = 0; session_selected = 0; session_cmd_selected = 0; search_selected = 0;
+                                tab = tab.prev();
+                                selected = 0; session_selected = 0; session_cmd_selected = 0; search_selected = 0; summary_selected = 0; workflow_selected = 0;
                             },
                             event::KeyCode::Char('l') => {
-                                tab = match tab {
-                                    Tab::Summary => Tab::PerCommand,
-                                    Tab::PerCommand => Tab::Sessions,
-                                    Tab::Sessions => Tab::Search,
-                                    Tab::Search => Tab::Aliases,
-                                    Tab::Aliases => Tab::Dangerous,
-                                    Tab::Dangerous => Tab::Directory,
-                                    Tab::Directory => Tab::Host,
-                                    Tab::Host => Tab::TimeOfDay,
-                                    Tab::TimeOfDay => Tab::Heatmap,
-                                    Tab::Heatmap => Tab::Summary,
-                                };
-                                selected = 0; session_selected = 0; session_cmd_selected = 0; search_selected = 0;
+                                tab = tab.next();
+                                selected = 0; session_selected = 0; session_cmd_selected = 0; search_selected = 0; summary_selected = 0; workflow_selected = 0;
                             },
                             event::KeyCode::Char('j') => {
                                 match tab {
+                                    Tab::Workflows => if workflow_selected + 1 < workflow_cache.len() { workflow_selected += 1; },
+                                    Tab::Summary => if summary_selected + 1 < summary_rows.len() { summary_selected += 1; },
                                     Tab::PerCommand => if selected + 1 < total { selected += 1; },
                                     Tab::Sessions => if session_selected + 1 < sessions.len() { session_selected += 1; session_cmd_selected = 0; },
                                     Tab::Search => if search_selected + 1 < search_results.len() { search_selected += 1; },
@@ -569,6 +829,8 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                             },
                             event::KeyCode::Char('k') => {
                                 match tab {
+                                    Tab::Workflows => if workflow_selected > 0 { workflow_selected -= 1; },
+                                    Tab::Summary => if summary_selected > 0 { summary_selected -= 1; },
                                     Tab::PerCommand => if selected > 0 { selected -= 1; },
                                     Tab::Sessions => if session_selected > 0 { session_selected -= 1; session_cmd_selected = 0; },
                                     Tab::Search => if search_selected > 0 { search_selected -= 1; },
@@ -577,11 +839,6 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                             },
                             event::KeyCode::Char('q') | event::KeyCode::Esc => running = false,
                             event::KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => running = false,
-                            event::KeyCode::Enter => {
-                                if tab == Tab::Sessions && !sessions.is_empty() && !sessions[session_selected].2.is_empty() {
-                                    session_cmd_selected = (session_cmd_selected + 1) % sessions[session_selected].2.len();
-                                }
-                            },
                             _ => {}
                         },
                         KeyMode::Emacs => match key.code {
@@ -589,6 +846,8 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                             event::KeyCode::Char('e') if key.modifiers.contains(event::KeyModifiers::CONTROL) => { selected = total.saturating_sub(1); },
                             event::KeyCode::Char('n') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
                                 match tab {
+                                    Tab::Workflows => if workflow_selected + 1 < workflow_cache.len() { workflow_selected += 1; },
+                                    Tab::Summary => if summary_selected + 1 < summary_rows.len() { summary_selected += 1; },
                                     Tab::PerCommand => if selected + 1 < total { selected += 1; },
                                     Tab::Sessions => if session_selected + 1 < sessions.len() { session_selected += 1; session_cmd_selected = 0; },
                                     Tab::Search => if search_selected + 1 < search_results.len() { search_selected += 1; },
@@ -597,6 +856,8 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                             },
                             event::KeyCode::Char('p') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
                                 match tab {
+                                    Tab::Workflows => if workflow_selected > 0 { workflow_selected -= 1; },
+                                    Tab::Summary => if summary_selected > 0 { summary_selected -= 1; },
                                     Tab::PerCommand => if selected > 0 { selected -= 1; },
                                     Tab::Sessions => if session_selected > 0 { session_selected -= 1; session_cmd_selected = 0; },
                                     Tab::Search => if search_selected > 0 { search_selected -= 1; },
@@ -605,11 +866,6 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                             },
                             event::KeyCode::Char('q') | event::KeyCode::Esc => running = false,
                             event::KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => running = false,
-                            event::KeyCode::Enter => {
-                                if tab == Tab::Sessions && !sessions.is_empty() && !sessions[session_selected].2.is_empty() {
-                                    session_cmd_selected = (session_cmd_selected + 1) % sessions[session_selected].2.len();
-                                }
-                            },
                             _ => {}
                         },
                         _ => match key.code {
@@ -618,6 +874,8 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                             event::KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => running = false,
                             event::KeyCode::Down => {
                                 match tab {
+                                    Tab::Workflows => if workflow_selected + 1 < workflow_cache.len() { workflow_selected += 1; },
+                                    Tab::Summary => if summary_selected + 1 < summary_rows.len() { summary_selected += 1; },
                                     Tab::PerCommand => if selected + 1 < total { selected += 1; },
                                     Tab::Sessions => if session_selected + 1 < sessions.len() { session_selected += 1; session_cmd_selected = 0; },
                                     Tab::Search => if search_selected + 1 < search_results.len() { search_selected += 1; },
@@ -626,6 +884,8 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                             },
                             event::KeyCode::Up => {
                                 match tab {
+                                    Tab::Workflows => if workflow_selected > 0 { workflow_selected -= 1; },
+                                    Tab::Summary => if summary_selected > 0 { summary_selected -= 1; },
                                     Tab::PerCommand => if selected > 0 { selected -= 1; },
                                     Tab::Sessions => if session_selected > 0 { session_selected -= 1; session_cmd_selected = 0; },
                                     Tab::Search => if search_selected > 0 { search_selected -= 1; },
@@ -633,39 +893,12 @@ pub fn run_tui(history: &Vec<HistoryEntry>, _args: &CliArgs) -> Result<()> {
                                 }
                             },
                             event::KeyCode::Right => {
-                                tab = match tab {
-                                    Tab::Summary => Tab::PerCommand,
-                                    Tab::PerCommand => Tab::Sessions,
-                                    Tab::Sessions => Tab::Search,
-                                    Tab::Search => Tab::Aliases,
-                                    Tab::Aliases => Tab::Dangerous,
-                                    Tab::Dangerous => Tab::Directory,
-                                    Tab::Directory => Tab::Host,
-                                    Tab::Host => Tab::TimeOfDay,
-                                    Tab::TimeOfDay => Tab::Heatmap,
-                                    Tab::Heatmap => Tab::Summary,
-                                };
-                                selected = 0; session_selected = 0; session_cmd_selected = 0; search_selected = 0;
+                                tab = tab.next();
+                                selected = 0; session_selected = 0; session_cmd_selected = 0; search_selected = 0; summary_selected = 0; workflow_selected = 0;
                             },
                             event::KeyCode::Left => {
-                                tab = match tab {
-                                    Tab::Summary => Tab::Search,
-                                    Tab::PerCommand => Tab::Summary,
-                                    Tab::Sessions => Tab::PerCommand,
-                                    Tab::Search => Tab::Sessions,
-                                    Tab::Aliases => Tab::Summary,
-                                    Tab::Dangerous => Tab::Aliases,
-                                    Tab::Directory => Tab::Dangerous,
-                                    Tab::Host => Tab::Directory,
-                                    Tab::TimeOfDay => Tab::Host,
-                                    Tab::Heatmap => Tab::TimeOfDay,
-                                };
-                                selected = 0; session_selected = 0; session_cmd_selected = 0; search_selected = 0;
-                            },
-                            event::KeyCode::Enter => {
-                                if tab == Tab::Sessions && !sessions.is_empty() && !sessions[session_selected].2.is_empty() {
-                                    session_cmd_selected = (session_cmd_selected + 1) % sessions[session_selected].2.len();
-                                }
+                                tab = tab.prev();
+                                selected = 0; session_selected = 0; session_cmd_selected = 0; search_selected = 0; summary_selected = 0; workflow_selected = 0;
                             },
                             _ => {}
                         }
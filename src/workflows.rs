@@ -0,0 +1,182 @@
+//! Workflow mining: frequent ordered command sequences across sessions
+//! Finds multi-step routines and turns them into shell scripts or functions
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::cli::AliasSyntax;
+use crate::models::HistoryEntry;
+use crate::tokenizer::command_path;
+
+/// Longest sequence mined, in commands
+const MAX_STEPS: usize = 5;
+
+/// A frequent sequence of consecutive commands
+#[derive(Debug, Clone, Serialize)]
+pub struct Workflow {
+    /// Representative command for each step (the most common spelling)
+    pub steps: Vec<String>,
+    /// Number of sessions containing the sequence
+    pub support: usize,
+    /// Share of sessions running the first step that went on to complete the sequence
+    pub confidence: f64,
+}
+
+impl Workflow {
+    /// Function name from the initials of each step, e.g. `wf_gfgrct`
+    pub fn name(&self) -> String {
+        let initials: String = self.steps.iter()
+            .flat_map(|s| command_path(s, 2))
+            .filter_map(|w| w.chars().find(|c| c.is_ascii_alphanumeric()))
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        format!("wf_{}", initials)
+    }
+}
+
+/// Key used to match steps across sessions: program and subcommand (`git commit`)
+fn step_key(command: &str) -> String {
+    command_path(command, 2).join(" ")
+}
+
+/// Mine sequences of 2 to 5 consecutive commands that occur in at least `min_support` sessions
+/// with at least `min_confidence`. Sequences contained in a longer result with the same
+/// support are dropped. Sorted by support, then length.
+pub fn mine_workflows(sessions: &[Vec<&HistoryEntry>], min_support: usize, min_confidence: f64) -> Vec<Workflow> {
+    // Most common spelling of every step key
+    let mut spellings: HashMap<String, HashMap<&str, usize>> = HashMap::new();
+    // Collapse immediate repeats so `make; make; make test` is `make → make test`
+    let keyed: Vec<Vec<String>> = sessions.iter().map(|session| {
+        let mut keys: Vec<String> = vec![];
+        for entry in session {
+            let key = step_key(&entry.command);
+            if key.is_empty() {
+                continue;
+            }
+            *spellings.entry(key.clone()).or_default().entry(entry.command.trim()).or_insert(0) += 1;
+            if keys.last() != Some(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }).collect();
+    let mut support: HashMap<Vec<String>, usize> = HashMap::new();
+    for keys in &keyed {
+        let mut seen: HashSet<&[String]> = HashSet::new();
+        for len in 1..=MAX_STEPS {
+            for window in keys.windows(len) {
+                if seen.insert(window) {
+                    *support.entry(window.to_vec()).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    let mut found: Vec<(Vec<String>, usize, f64)> = support.iter()
+        .filter(|(seq, count)| seq.len() >= 2 && **count >= min_support.max(1))
+        .filter_map(|(seq, count)| {
+            let first = support.get(&seq[..1]).copied().unwrap_or(*count);
+            let confidence = *count as f64 / first as f64;
+            (confidence >= min_confidence).then(|| (seq.clone(), *count, confidence))
+        })
+        .collect();
+    // Keep only closed sequences
+    let closed: Vec<bool> = found.iter().map(|(seq, count, _)| {
+        !found.iter().any(|(other, other_count, _)| {
+            other.len() > seq.len() && other_count == count && other.windows(seq.len()).any(|w| w == seq.as_slice())
+        })
+    }).collect();
+    let mut keep = closed.into_iter();
+    found.retain(|_| keep.next().unwrap_or(false));
+    found.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.len().cmp(&a.0.len())).then_with(|| a.0.cmp(&b.0)));
+    found.into_iter()
+        .take(20)
+        .map(|(seq, support, confidence)| Workflow {
+            steps: seq.iter().map(|key| {
+                spellings.get(key)
+                    .and_then(|s| s.iter().max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0))))
+                    .map(|(cmd, _)| cmd.to_string())
+                    .unwrap_or_else(|| key.clone())
+            }).collect(),
+            support,
+            confidence,
+        })
+        .collect()
+}
+
+/// Standalone bash script that runs a workflow, stopping at the first failure
+pub fn workflow_script(workflow: &Workflow) -> String {
+    let mut script = format!(
+        "#!/usr/bin/env bash\n# Workflow mined by heist: seen in {} sessions (confidence {:.2})\nset -euo pipefail\n\n",
+        workflow.support, workflow.confidence
+    );
+    for step in &workflow.steps {
+        script.push_str(step);
+        script.push('\n');
+    }
+    script
+}
+
+/// Shell function that runs a workflow, stopping at the first failure
+pub fn workflow_function(workflow: &Workflow, syntax: AliasSyntax) -> String {
+    let name = workflow.name();
+    match syntax {
+        AliasSyntax::Bash | AliasSyntax::Zsh => {
+            format!("{}() {{\n    {}\n}}", name, workflow.steps.join(" &&\n    "))
+        },
+        AliasSyntax::Fish => {
+            format!("function {}\n    {}\nend", name, workflow.steps.join(" &&\n    "))
+        },
+        AliasSyntax::Nu => {
+            format!("def {} [] {{\n    {}\n}}", name, workflow.steps.join("\n    "))
+        },
+    }
+}
+
+/// Print numbered workflows
+pub fn print_workflows(workflows: &[Workflow]) {
+    println!("\nFrequent workflows (command sequences across sessions):");
+    if workflows.is_empty() {
+        println!("No frequent workflows found. Try lowering --min-support or --min-confidence.");
+        return;
+    }
+    for (i, w) in workflows.iter().enumerate() {
+        println!("{:>2}. [{} sessions, confidence {:.2}] {}", i + 1, w.support, w.confidence, w.steps.join(" → "));
+    }
+    println!("\nUse --workflow-script N to generate a script (or a function with --alias-shell).");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(commands: &[&str]) -> Vec<HistoryEntry> {
+        commands.iter()
+            .map(|c| HistoryEntry { timestamp: None, command: c.to_string(), session_id: None, ..Default::default() })
+            .collect()
+    }
+
+    #[test]
+    fn test_mine_workflows() {
+        let raw = [
+            session(&["git fetch", "git rebase origin/main", "cargo test", "git push"]),
+            session(&["ls", "git fetch", "git rebase origin/main", "cargo test", "cargo test", "git push"]),
+            session(&["git fetch", "git rebase origin/dev", "cargo test", "git push", "vim x"]),
+            session(&["git fetch", "git status"]),
+        ];
+        let sessions: Vec<Vec<&HistoryEntry>> = raw.iter().map(|s| s.iter().collect()).collect();
+        let workflows = mine_workflows(&sessions, 3, 0.5);
+        assert_eq!(workflows.len(), 1);
+        let w = &workflows[0];
+        assert_eq!(w.steps, vec!["git fetch", "git rebase origin/main", "cargo test", "git push"]);
+        assert_eq!(w.support, 3);
+        assert!((w.confidence - 0.75).abs() < f64::EPSILON);
+        assert_eq!(w.name(), "wf_gfgrctgp");
+        assert!(workflow_script(w).ends_with("git fetch\ngit rebase origin/main\ncargo test\ngit push\n"));
+        assert_eq!(workflow_function(w, AliasSyntax::Bash).lines().count(), 6);
+        // Only 3 of the 4 sessions running `git fetch` complete the sequence, but every rebase does
+        let strict = mine_workflows(&sessions, 3, 0.8);
+        assert_eq!(strict.len(), 1);
+        assert_eq!(strict[0].steps[0], "git rebase origin/main");
+    }
+}